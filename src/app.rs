@@ -1,13 +1,23 @@
 use parking_lot::Mutex;
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use std::error::Error;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::audio_system::AudioSystem;
+use crate::config::{get_crossfade, get_library_sort, get_play_order, get_playlist_imports};
+use crate::controls::album_browser::AlbumBrowser;
+use crate::controls::folder_tree::FolderTree;
+use crate::controls::graphic_eq::GraphicEq;
 use crate::controls::keybindings::Keybindings;
-use crate::controls::music_library::MusicLibrary;
-use crate::controls::playback_state::PlaybackState;
+use crate::controls::library_roots::LibraryRoots;
+use crate::controls::library_search::LibrarySearch;
+use crate::controls::library_watcher::LibraryWatcher;
+use crate::controls::list_viewport::ListViewport;
+use crate::controls::music_library::{LibraryLayout, MusicLibrary};
+use crate::controls::play_queue::PlayQueue;
+use crate::controls::playback_state::{PlaybackState, PlaybackStatus};
+use crate::controls::playlist::Playlists;
 use crate::controls::sound_control::SoundControl;
 use crate::{log_debug, log_error};
 
@@ -15,27 +25,74 @@ use crate::{log_debug, log_error};
 ///
 /// The `App` struct is the central coordinator of the application, managing:
 /// - Audio system and playback
-/// - Music library browsing
+/// - Music library and playlist browsing
 /// - User input handling via keybindings
-/// - UI state (help overlay visibility)
+/// - UI state (help and scan report overlays, equalizer and play queue focus,
+///   library search)
 pub struct App {
     audio_system: Arc<Mutex<AudioSystem>>,
     library: Arc<Mutex<MusicLibrary>>,
+    playlists: Arc<Mutex<Playlists>>,
+    play_queue: Arc<Mutex<PlayQueue>>,
     playback: Arc<Mutex<PlaybackState>>,
+    /// The audio system's equalizer state, to reach without locking it
+    graphic_eq: Arc<Mutex<GraphicEq>>,
+    search: Arc<Mutex<LibrarySearch>>,
+    /// Scroll position of the track panel
+    viewport: Arc<Mutex<ListViewport>>,
+    folder_tree: Arc<Mutex<FolderTree>>,
+    album_browser: Arc<Mutex<AlbumBrowser>>,
     keybindings: Keybindings,
+    /// Keeps the music directories watched for as long as the app runs
+    _library_watcher: Option<LibraryWatcher>,
     pub show_help: bool,
+    pub show_scan_report: bool,
+    pub equalizer_focused: bool,
+    pub queue_focused: bool,
+    pub library_layout: LibraryLayout,
 }
 
 impl App {
-    pub fn new(root_dir: PathBuf) -> Result<Self, Box<dyn Error>> {
+    pub fn new(roots: LibraryRoots) -> Result<Self, Box<dyn Error>> {
         // Create initial components
-        let library = MusicLibrary::new(root_dir)?;
+        let mut library = MusicLibrary::new(roots)?;
+        library.sort = get_library_sort();
         let library = Arc::new(Mutex::new(library));
-        let playback = Arc::new(Mutex::new(PlaybackState::default()));
+        MusicLibrary::scan_in_background(&library);
+        let library_watcher = match LibraryWatcher::new(Arc::clone(&library)) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                log_error!("Cannot watch the music directories for changes: {}", e);
+                None
+            }
+        };
+        let mut playlists = Playlists::load();
+        for path in get_playlist_imports() {
+            if let Err(e) = playlists.import(&path) {
+                log_error!("Failed to import playlist {:?}: {}", path, e);
+            }
+        }
+        let playlists = Arc::new(Mutex::new(playlists));
+        let play_queue = Arc::new(Mutex::new(PlayQueue::default()));
+        let (crossfade_enabled, crossfade) = get_crossfade();
+        let (repeat, shuffle) = get_play_order();
+        let playback = Arc::new(Mutex::new(PlaybackState {
+            crossfade_enabled,
+            crossfade,
+            repeat,
+            shuffle,
+            ..PlaybackState::default()
+        }));
 
         // Initialize audio system with references to necessary components
         // [[CHECKPOINT]]
-        let audio_system = AudioSystem::new(Arc::clone(&library), Arc::clone(&playback))?;
+        let audio_system = AudioSystem::new(
+            Arc::clone(&library),
+            Arc::clone(&playlists),
+            Arc::clone(&play_queue),
+            Arc::clone(&playback),
+        )?;
+        let graphic_eq = audio_system.get_graphic_eq_state();
 
         #[allow(clippy::arc_with_non_send_sync)]
         let audio_system = Arc::new(Mutex::new(audio_system));
@@ -43,21 +100,51 @@ impl App {
         Ok(Self {
             audio_system,
             library,
+            playlists,
+            play_queue,
             playback,
+            graphic_eq,
+            search: Arc::new(Mutex::new(LibrarySearch::default())),
+            viewport: Arc::new(Mutex::new(ListViewport::default())),
+            folder_tree: Arc::new(Mutex::new(FolderTree::default())),
+            album_browser: Arc::new(Mutex::new(AlbumBrowser::default())),
             keybindings: Keybindings::new(),
+            _library_watcher: library_watcher,
             show_help: false,
+            show_scan_report: false,
+            equalizer_focused: false,
+            queue_focused: false,
+            library_layout: LibraryLayout::default(),
         })
     }
 
     pub fn update(&mut self) {
         let mut audio = self.audio_system.lock();
+        audio.refresh_library();
         // Update playback state update visualizer with it
         audio.update_playback();
+        drop(audio);
+
+        // Keep search matches and the library layouts pointing at the right
+        // tracks
+        self.search.lock().refresh(&self.library.lock());
+        self.refresh_layout();
     }
 }
 
 impl App {
     pub fn handle_key_event(&mut self, key_event: KeyEvent) -> Result<bool, Box<dyn Error>> {
+        // While a search query is typed every key goes to it
+        if self.search.lock().editing {
+            self.handle_search_key(key_event);
+            return Ok(true);
+        }
+        // So does the name of an equalizer preset being saved
+        if self.graphic_eq.lock().naming.is_some() {
+            self.handle_preset_name_key(key_event);
+            return Ok(true);
+        }
+
         // Check if the '?' key was pressed to toggle help
         if key_event.code == KeyCode::Char('?') {
             self.show_help = !self.show_help;
@@ -70,14 +157,96 @@ impl App {
             return Ok(true);
         }
 
+        // The scan report closes the same way
+        if self.show_scan_report {
+            self.show_scan_report = false;
+            return Ok(true);
+        }
+
+        // While the equalizer has focus its bindings shadow the global ones
+        if self.equalizer_focused {
+            if let Some(action) = self.keybindings.get_equalizer_action(&key_event) {
+                match action.name.as_str() {
+                    "eq_band_previous" => {
+                        self.graphic_eq.lock().select_previous_band();
+                    }
+                    "eq_band_next" => {
+                        self.graphic_eq.lock().select_next_band();
+                    }
+                    "eq_gain_up" => {
+                        self.audio_system.lock().adjust_eq_band(1.0);
+                    }
+                    "eq_gain_down" => {
+                        self.audio_system.lock().adjust_eq_band(-1.0);
+                    }
+                    "eq_preset_previous" => {
+                        self.audio_system.lock().cycle_eq_preset(-1);
+                    }
+                    "eq_preset_next" => {
+                        self.audio_system.lock().cycle_eq_preset(1);
+                    }
+                    "eq_preset_save" => {
+                        self.graphic_eq.lock().naming = Some(String::new());
+                    }
+                    "eq_preset_delete" => {
+                        self.audio_system.lock().delete_eq_preset();
+                    }
+                    "eq_reset" => {
+                        self.audio_system.lock().reset_eq();
+                    }
+                    "toggle_equalizer_focus" => {
+                        self.equalizer_focused = false;
+                    }
+                    _ => {
+                        log_debug!("Unhandled equalizer action: {}", action.name);
+                    }
+                }
+                return Ok(true);
+            }
+        }
+
+        // The same goes for the play queue panel
+        if self.queue_focused {
+            if let Some(action) = self.keybindings.get_queue_action(&key_event) {
+                match action.name.as_str() {
+                    "queue_select_previous" => {
+                        self.play_queue.lock().select_previous();
+                    }
+                    "queue_select_next" => {
+                        self.play_queue.lock().select_next();
+                    }
+                    "queue_move_up" => {
+                        self.play_queue.lock().move_selected_up();
+                    }
+                    "queue_move_down" => {
+                        self.play_queue.lock().move_selected_down();
+                    }
+                    "queue_play" => {
+                        self.play_queued()?;
+                    }
+                    "queue_remove" => {
+                        self.play_queue.lock().remove_selected();
+                    }
+                    "queue_clear" => {
+                        self.play_queue.lock().clear();
+                    }
+                    "toggle_queue_focus" => {
+                        self.queue_focused = false;
+                    }
+                    _ => {
+                        log_debug!("Unhandled queue action: {}", action.name);
+                    }
+                }
+                self.audio_system.lock().refresh_upcoming();
+                return Ok(true);
+            }
+        }
+
         // Check if we have a keybinding for this key
         if let Some(action) = self.keybindings.get_action(&key_event) {
             match action.name.as_str() {
                 "play_selected" => {
-                    let current_index = self.library.lock().current_index;
-                    self.library.lock().select_track(current_index);
-                    self.playback.lock().current_track = Some(current_index);
-                    self.audio_system.lock().play_track(None)?;
+                    self.play_selected()?;
                 }
                 "toggle_playback" => {
                     if let Err(err) = self.audio_system.lock().toggle_playback() {
@@ -85,13 +254,142 @@ impl App {
                     }
                 }
                 "select_previous" => {
-                    self.library.lock().select_previous();
+                    if self.playlists.lock().view.is_some() {
+                        self.playlists.lock().select_previous_entry();
+                    } else if self.library_layout == LibraryLayout::Folders {
+                        self.folder_tree.lock().select_previous();
+                    } else if self.library_layout == LibraryLayout::Albums {
+                        self.album_browser.lock().select_previous();
+                    } else if self.is_filtered() {
+                        self.jump_to_match(false);
+                    } else {
+                        self.library.lock().select_previous();
+                    }
                     log_debug!("Selected previous track");
                 }
                 "select_next" => {
-                    self.library.lock().select_next();
+                    if self.playlists.lock().view.is_some() {
+                        self.playlists.lock().select_next_entry();
+                    } else if self.library_layout == LibraryLayout::Folders {
+                        self.folder_tree.lock().select_next();
+                    } else if self.library_layout == LibraryLayout::Albums {
+                        self.album_browser.lock().select_next();
+                    } else if self.is_filtered() {
+                        self.jump_to_match(true);
+                    } else {
+                        self.library.lock().select_next();
+                    }
                     log_debug!("Selected next track");
                 }
+                "page_up" => {
+                    let page = self.viewport.lock().page() as isize;
+                    self.move_cursor(-page);
+                }
+                "page_down" => {
+                    let page = self.viewport.lock().page() as isize;
+                    self.move_cursor(page);
+                }
+                "select_first" => {
+                    self.move_cursor(isize::MIN);
+                }
+                "select_last" => {
+                    self.move_cursor(isize::MAX);
+                }
+                "center_on_playing" => {
+                    self.center_on_playing();
+                }
+                "cycle_library_layout" => {
+                    self.library_layout = self.library_layout.next();
+                    self.playlists.lock().view = None;
+                    self.refresh_layout();
+                }
+                "toggle_folder" => match self.library_layout {
+                    LibraryLayout::Folders => self.folder_tree.lock().toggle_selected(),
+                    LibraryLayout::Albums => self.change_column(true),
+                    LibraryLayout::Tracks => {}
+                },
+                "collapse_folder" => match self.library_layout {
+                    LibraryLayout::Folders => self.folder_tree.lock().collapse_selected(),
+                    LibraryLayout::Albums => self.change_column(false),
+                    LibraryLayout::Tracks => {}
+                },
+                "start_search" => {
+                    // Searching looks at the library's track list, so show it
+                    self.playlists.lock().view = None;
+                    self.library_layout = LibraryLayout::Tracks;
+                    let cursor = self.library.lock().current_index;
+                    self.search.lock().start(cursor);
+                }
+                "next_match" => {
+                    self.jump_to_match(true);
+                }
+                "previous_match" => {
+                    self.jump_to_match(false);
+                }
+                "toggle_search_filter" => {
+                    self.search.lock().toggle_filter();
+                    // Keep the cursor on a listed track
+                    let cursor = self.library.lock().current_index;
+                    if self.is_filtered() && self.search.lock().match_for(cursor).is_none() {
+                        self.jump_to_match(true);
+                    }
+                }
+                "clear_search" => {
+                    self.search.lock().clear();
+                }
+                "next_view" => {
+                    self.playlists.lock().view_next();
+                }
+                "previous_view" => {
+                    self.playlists.lock().view_previous();
+                }
+                "add_to_playlist" | "add_to_new_playlist" => {
+                    let track = {
+                        let cursor = match self.library_layout {
+                            LibraryLayout::Tracks => Some(self.library.lock().current_index),
+                            LibraryLayout::Folders => self.folder_tree.lock().selected_track(),
+                            LibraryLayout::Albums => self.album_browser.lock().selected_track(),
+                        };
+                        let library = self.library.lock();
+                        cursor.and_then(|index| library.tracks.get(index).cloned())
+                    };
+                    let mut playlists = self.playlists.lock();
+                    if let (Some(track), None) = (track, playlists.view) {
+                        if action.name == "add_to_playlist" {
+                            playlists.add_to_target(&track);
+                        } else {
+                            playlists.add_to_new(&track);
+                        }
+                    }
+                }
+                "remove_from_playlist" => {
+                    self.playlists.lock().remove_selected_entry();
+                    self.audio_system.lock().refresh_upcoming();
+                }
+                "add_to_queue" | "play_next" => {
+                    let paths = self.selected_paths();
+                    let mut play_queue = self.play_queue.lock();
+                    if action.name == "add_to_queue" {
+                        paths.iter().for_each(|path| play_queue.push(path));
+                    } else {
+                        // Queued next one by one from the last, they keep their order
+                        paths
+                            .iter()
+                            .rev()
+                            .for_each(|path| play_queue.push_next(path));
+                    }
+                    drop(play_queue);
+                    self.audio_system.lock().refresh_upcoming();
+                }
+                "toggle_queue_focus" => {
+                    self.queue_focused = !self.queue_focused;
+                    self.equalizer_focused = false;
+                }
+                "export_playlist" => {
+                    if let Err(e) = self.playlists.lock().export_shown() {
+                        log_error!("Failed to export playlist: {}", e);
+                    }
+                }
                 "seek_forward" => {
                     self.audio_system.lock().seek_forward(None);
                 }
@@ -105,10 +403,16 @@ impl App {
                     self.audio_system.lock().adjust_volume(5.0);
                 }
                 "pitch_left" => {
-                    self.audio_system.lock().adjust_pitch(-5.0);
+                    self.audio_system.lock().adjust_pitch(-1.0);
                 }
                 "pitch_right" => {
-                    self.audio_system.lock().adjust_pitch(5.0);
+                    self.audio_system.lock().adjust_pitch(1.0);
+                }
+                "tempo_down" => {
+                    self.audio_system.lock().adjust_tempo(-5.0);
+                }
+                "tempo_up" => {
+                    self.audio_system.lock().adjust_tempo(5.0);
                 }
                 "bass_up" => {
                     self.audio_system.lock().adjust_bass(5.0);
@@ -125,6 +429,42 @@ impl App {
                 "stop" => {
                     self.audio_system.lock().stop();
                 }
+                "next_track" => {
+                    self.audio_system.lock().next_track();
+                }
+                "prev_track" => {
+                    if let Err(err) = self.audio_system.lock().previous_track() {
+                        log_error!("Error going back a track: {}", err);
+                    }
+                }
+                "cycle_replay_gain" => {
+                    self.audio_system.lock().cycle_replay_gain_mode();
+                }
+                "toggle_crossfade" => {
+                    self.audio_system.lock().toggle_crossfade();
+                }
+                "cycle_repeat" => {
+                    self.audio_system.lock().cycle_repeat_mode();
+                }
+                "cycle_shuffle" => {
+                    self.audio_system.lock().cycle_shuffle_mode();
+                }
+                "cycle_library_sort" => {
+                    self.audio_system.lock().cycle_library_sort();
+                    // Keep the cursor's row in view in its new place
+                    self.search.lock().refresh(&self.library.lock());
+                    self.refresh_layout();
+                    if let Some(row) = self.cursor_row() {
+                        self.viewport.lock().center_on(row);
+                    }
+                }
+                "toggle_equalizer_focus" => {
+                    self.equalizer_focused = !self.equalizer_focused;
+                    self.queue_focused = false;
+                }
+                "show_scan_report" => {
+                    self.show_scan_report = true;
+                }
                 "quit" => {
                     log_debug!("Quit key pressed");
                     return Ok(false);
@@ -146,17 +486,404 @@ impl App {
         log_debug!("Unhandled key event: {:?}", key_event);
         Ok(true)
     }
+
+    /// Type the name of the equalizer preset being saved; Enter saves it and
+    /// Esc leaves it unsaved
+    fn handle_preset_name_key(&mut self, key_event: KeyEvent) {
+        let mut graphic_eq = self.graphic_eq.lock();
+        let Some(name) = graphic_eq.naming.as_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c)
+                if !key_event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                name.push(c);
+            }
+            KeyCode::Backspace => {
+                name.pop();
+            }
+            KeyCode::Enter => {
+                let name = graphic_eq.naming.take().unwrap_or_default();
+                drop(graphic_eq);
+                self.audio_system.lock().save_eq_preset(&name);
+            }
+            KeyCode::Esc => graphic_eq.naming = None,
+            _ => {}
+        }
+    }
 }
 
 impl App {
+    /// Keys typed into the search query: characters extend it, Backspace
+    /// shortens it, Enter keeps the matches and Esc drops them
+    fn handle_search_key(&mut self, key_event: KeyEvent) {
+        let mut search = self.search.lock();
+        let mut library = self.library.lock();
+        match key_event.code {
+            KeyCode::Char(c)
+                if !key_event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                search.push(c, &library);
+            }
+            KeyCode::Backspace if search.query.is_empty() => {
+                library.current_index = search.cancel();
+                return;
+            }
+            KeyCode::Backspace => search.pop(&library),
+            KeyCode::Enter => {
+                search.confirm();
+                return;
+            }
+            KeyCode::Esc => {
+                library.current_index = search.cancel();
+                return;
+            }
+            KeyCode::Down => {
+                if let Some(index) = search.next_match(library.current_index) {
+                    library.current_index = index;
+                }
+                return;
+            }
+            KeyCode::Up => {
+                if let Some(index) = search.previous_match(library.current_index) {
+                    library.current_index = index;
+                }
+                return;
+            }
+            _ => return,
+        }
+
+        // Follow the best match as the query changes
+        if let Some(index) = search.best() {
+            library.current_index = index;
+        }
+    }
+
+    /// Whether the library view lists only search matches
+    fn is_filtered(&self) -> bool {
+        let search = self.search.lock();
+        search.filter && search.is_active()
+    }
+
+    /// Move the cursor to the next or previous search match
+    fn jump_to_match(&mut self, forward: bool) {
+        let search = self.search.lock();
+        let mut library = self.library.lock();
+        let index = if forward {
+            search.next_match(library.current_index)
+        } else {
+            search.previous_match(library.current_index)
+        };
+        if let Some(index) = index {
+            library.current_index = index;
+        }
+    }
+
+    /// Regroup the folder tree or album browser when it is shown
+    fn refresh_layout(&mut self) {
+        let library = self.library.lock();
+        match self.library_layout {
+            LibraryLayout::Tracks => {}
+            LibraryLayout::Folders => self.folder_tree.lock().refresh(&library),
+            LibraryLayout::Albums => self.album_browser.lock().refresh(&library),
+        }
+    }
+
+    /// Move the album browser's cursor a column to the right or left and
+    /// scroll the panel to its row there
+    fn change_column(&mut self, forward: bool) {
+        let mut album_browser = self.album_browser.lock();
+        if forward {
+            album_browser.enter_column();
+        } else {
+            album_browser.leave_column();
+        }
+        self.viewport.lock().center_on(album_browser.cursor());
+    }
+
+    /// What the rows of the track panel stand for: the library indices of the
+    /// listed tracks, the rows of the folder tree or of the album browser's
+    /// current column, or the entries of the shown playlist
+    fn listed_rows(&self) -> Vec<usize> {
+        let playlists = self.playlists.lock();
+        if let Some(playlist) = playlists.shown() {
+            return (0..playlist.entries.len()).collect();
+        }
+        match self.library_layout {
+            LibraryLayout::Folders => return (0..self.folder_tree.lock().rows.len()).collect(),
+            LibraryLayout::Albums => return (0..self.album_browser.lock().column_len()).collect(),
+            LibraryLayout::Tracks => {}
+        }
+        let search = self.search.lock();
+        (0..self.library.lock().tracks.len())
+            .filter(|&index| search.shows(index))
+            .collect()
+    }
+
+    /// Row of the track panel the cursor is on, None while it is on a track
+    /// that is filtered out
+    fn cursor_row(&self) -> Option<usize> {
+        let playlists = self.playlists.lock();
+        if playlists.view.is_some() {
+            return Some(playlists.selected_entry);
+        }
+        drop(playlists);
+        match self.library_layout {
+            LibraryLayout::Folders => Some(self.folder_tree.lock().selected),
+            LibraryLayout::Albums => Some(self.album_browser.lock().cursor()),
+            LibraryLayout::Tracks => {
+                let cursor = self.library.lock().current_index;
+                self.listed_rows().iter().position(|&index| index == cursor)
+            }
+        }
+    }
+
+    /// Move the cursor of the track panel by a number of rows, stopping at the
+    /// first and last row, and scroll the panel along by as much
+    fn move_cursor(&mut self, rows: isize) {
+        let listed = self.listed_rows();
+        let Some(&last) = listed.last() else {
+            return;
+        };
+        let mut playlists = self.playlists.lock();
+        let mut library = self.library.lock();
+        let mut folder_tree = self.folder_tree.lock();
+        let mut album_browser = self.album_browser.lock();
+        let cursor = match (playlists.view, self.library_layout) {
+            (Some(_), _) => playlists.selected_entry,
+            (None, LibraryLayout::Folders) => folder_tree.selected,
+            (None, LibraryLayout::Albums) => album_browser.cursor(),
+            (None, LibraryLayout::Tracks) => library.current_index,
+        };
+        // A cursor on a row filtered out counts as on the next listed one
+        let row = listed
+            .iter()
+            .position(|&position| position >= cursor)
+            .unwrap_or(listed.len() - 1);
+        let target = listed
+            .get(row.saturating_add_signed(rows))
+            .copied()
+            .unwrap_or(last);
+        match (playlists.view, self.library_layout) {
+            (Some(_), _) => playlists.selected_entry = target,
+            (None, LibraryLayout::Folders) => folder_tree.selected = target,
+            (None, LibraryLayout::Albums) => album_browser.select(target),
+            (None, LibraryLayout::Tracks) => library.current_index = target,
+        }
+        self.viewport.lock().scroll(rows, listed.len());
+    }
+
+    /// Put the cursor on the playing track and scroll it to the middle of the
+    /// track panel. The library is shown unless the playing playlist is.
+    fn center_on_playing(&mut self) {
+        let row = {
+            let mut playlists = self.playlists.lock();
+            match playlists.playing {
+                Some((playlist, entry)) if playlists.view == Some(playlist) => {
+                    playlists.selected_entry = entry;
+                    Some(entry)
+                }
+                _ => {
+                    playlists.view = None;
+                    drop(playlists);
+                    let Some(index) = self.playback.lock().current_track else {
+                        return;
+                    };
+                    let revealed = match self.library_layout {
+                        LibraryLayout::Tracks => None,
+                        LibraryLayout::Folders => {
+                            let library = self.library.lock();
+                            Some(self.folder_tree.lock().reveal(index, &library))
+                        }
+                        LibraryLayout::Albums => Some(self.album_browser.lock().reveal(index)),
+                    };
+                    if let Some(row) = revealed {
+                        if let Some(row) = row {
+                            self.viewport.lock().center_on(row);
+                        }
+                        return;
+                    }
+                    let mut search = self.search.lock();
+                    if !search.shows(index) {
+                        search.filter = false;
+                    }
+                    drop(search);
+                    self.library.lock().current_index = index;
+                    self.listed_rows()
+                        .iter()
+                        .position(|&listed| listed == index)
+                }
+            }
+        };
+        if let Some(row) = row {
+            self.viewport.lock().center_on(row);
+        }
+    }
+
+    /// Play the track under the cursor: a library track, or a playlist entry
+    /// that playback then continues the playlist from. A folder plays its
+    /// tracks in order; an artist or album is added to the play queue instead,
+    /// which starts playing when nothing plays.
+    fn play_selected(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.playlists.lock().view.is_none() {
+            match self.library_layout {
+                LibraryLayout::Tracks => {}
+                LibraryLayout::Folders => {
+                    let indices = self.folder_tree.lock().selected_tracks();
+                    return self.play_tracks(&indices);
+                }
+                LibraryLayout::Albums => {
+                    let indices = self.album_browser.lock().tracks_to_queue();
+                    self.queue_tracks(&indices);
+                    if self.playback.lock().status == PlaybackStatus::Stopped {
+                        self.audio_system.lock().next_track();
+                    }
+                    return Ok(());
+                }
+            }
+        }
+
+        let (view, entry, entry_path) = {
+            let playlists = self.playlists.lock();
+            let entry_path = playlists
+                .shown()
+                .and_then(|playlist| playlist.entries.get(playlists.selected_entry))
+                .map(|entry| entry.path.clone());
+            (playlists.view, playlists.selected_entry, entry_path)
+        };
+
+        let index = match (view, entry_path) {
+            (None, _) => self.library.lock().current_index,
+            (Some(_), Some(path)) => match self.library.lock().index_of(&path) {
+                Some(index) => index,
+                None => {
+                    log_error!("Cannot play {:?}: not in the library", path);
+                    return Ok(());
+                }
+            },
+            (Some(_), None) => return Ok(()),
+        };
+        if self.library.lock().select_track(index).is_none() {
+            // Nothing to play until the scan has found tracks
+            return Ok(());
+        }
+
+        self.playlists.lock().playing = view.map(|playlist| (playlist, entry));
+        self.audio_system.lock().play_track(Some(index))
+    }
+
+    /// Play the first of several tracks and line up the rest to follow it,
+    /// ahead of anything queued before
+    fn play_tracks(&mut self, indices: &[usize]) -> Result<(), Box<dyn Error>> {
+        let Some((&first, rest)) = indices.split_first() else {
+            return Ok(());
+        };
+        {
+            let library = self.library.lock();
+            let mut play_queue = self.play_queue.lock();
+            for track in rest.iter().rev().filter_map(|&i| library.tracks.get(i)) {
+                play_queue.push_next(&track.path);
+            }
+        }
+        self.playlists.lock().playing = None;
+        self.library.lock().select_track(first);
+        self.audio_system.lock().play_track(Some(first))
+    }
+
+    /// Add tracks to the end of the play queue in their order, leaving what
+    /// plays alone
+    fn queue_tracks(&mut self, indices: &[usize]) {
+        {
+            let library = self.library.lock();
+            let mut play_queue = self.play_queue.lock();
+            for track in indices.iter().filter_map(|&i| library.tracks.get(i)) {
+                play_queue.push(&track.path);
+            }
+        }
+        self.audio_system.lock().refresh_upcoming();
+    }
+
+    /// Paths of the tracks under the cursor: a track in the library or the
+    /// shown playlist, everything below a folder of the folder tree, or an
+    /// artist or album of the album browser
+    fn selected_paths(&self) -> Vec<PathBuf> {
+        let playlists = self.playlists.lock();
+        if let Some(playlist) = playlists.shown() {
+            return playlist
+                .entries
+                .get(playlists.selected_entry)
+                .map(|entry| entry.path.clone())
+                .into_iter()
+                .collect();
+        }
+
+        let library = self.library.lock();
+        let indices = match self.library_layout {
+            LibraryLayout::Tracks => vec![library.current_index],
+            LibraryLayout::Folders => self.folder_tree.lock().selected_tracks(),
+            LibraryLayout::Albums => self.album_browser.lock().selected_tracks(),
+        };
+        indices
+            .into_iter()
+            .filter_map(|index| library.tracks.get(index))
+            .map(|track| track.path.clone())
+            .collect()
+    }
+
+    /// Take the selected entry out of the play queue and play it right away
+    fn play_queued(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(path) = self.play_queue.lock().remove_selected() else {
+            return Ok(());
+        };
+        let Some(index) = self.library.lock().index_of(&path) else {
+            log_error!("Cannot play {:?}: not in the library", path);
+            return Ok(());
+        };
+        self.library.lock().select_track(index);
+        self.audio_system.lock().play_track(Some(index))
+    }
+
     pub fn get_library_state(&self) -> Arc<Mutex<MusicLibrary>> {
         Arc::clone(&self.library)
     }
 
+    pub fn get_search_state(&self) -> Arc<Mutex<LibrarySearch>> {
+        Arc::clone(&self.search)
+    }
+
+    pub fn get_playlists_state(&self) -> Arc<Mutex<Playlists>> {
+        Arc::clone(&self.playlists)
+    }
+
+    pub fn get_folder_tree_state(&self) -> Arc<Mutex<FolderTree>> {
+        Arc::clone(&self.folder_tree)
+    }
+
+    pub fn get_album_browser_state(&self) -> Arc<Mutex<AlbumBrowser>> {
+        Arc::clone(&self.album_browser)
+    }
+
+    pub fn get_viewport_state(&self) -> Arc<Mutex<ListViewport>> {
+        Arc::clone(&self.viewport)
+    }
+
+    pub fn get_play_queue_state(&self) -> Arc<Mutex<PlayQueue>> {
+        Arc::clone(&self.play_queue)
+    }
+
     pub fn get_sound_state(&self) -> Arc<Mutex<SoundControl>> {
         Arc::clone(&self.audio_system.lock().get_sound_state())
     }
 
+    pub fn get_graphic_eq_state(&self) -> Arc<Mutex<GraphicEq>> {
+        Arc::clone(&self.graphic_eq)
+    }
+
     pub fn get_playback_state(&self) -> Arc<Mutex<PlaybackState>> {
         Arc::clone(&self.playback)
     }
@@ -5,53 +5,107 @@ use std::time::Duration;
 
 use parking_lot::Mutex;
 
+use crate::config::{get_previous_restart, get_replay_gain, get_spectrum_settings};
 use crate::controls::audio_engine::AudioEngine;
+use crate::controls::graphic_eq::GraphicEq;
+use crate::controls::loudness::LoudnessScanner;
 use crate::controls::music_library::MusicLibrary;
-use crate::controls::playback_state::{PlaybackState, PlaybackStatus};
+use crate::controls::play_history::PlayHistory;
+use crate::controls::play_queue::PlayQueue;
+use crate::controls::playback_state::{PlaybackState, PlaybackStatus, RepeatMode, ShuffleMode};
+use crate::controls::playlist::Playlists;
+use crate::controls::shuffle::ShuffleRound;
 use crate::controls::sound_control::SoundControl;
-use crate::controls::spectrum::Spectrum;
+use crate::controls::spectrum::{LiveSpectrum, Spectrum, SpectrumFrame, SpectrumSource};
 use crate::{log_debug, log_error};
 
+/// How long before the end of a track the next one is decoded and appended
+const PRELOAD_AHEAD: Duration = Duration::from_secs(10);
+
 /// Primary audio system that manages playback_state, sound_control processing, music library,
 /// and visualization.
 ///
 /// The `AudioSystem` is the central component responsible for:
 /// - Playing and controlling audio playback_state
-/// - Applying audio effects and adjustments (volume, bass, treble, pitch)
+/// - Applying audio effects and adjustments (volume, bass, treble, pitch, tempo)
 /// - Track selection and progression
 /// - Audio visualization data processing
 pub struct AudioSystem {
     library: Arc<Mutex<MusicLibrary>>,
+    playlists: Arc<Mutex<Playlists>>,
+    play_queue: Arc<Mutex<PlayQueue>>,
     playback_state: Arc<Mutex<PlaybackState>>,
     sound_control: Arc<Mutex<SoundControl>>,
+    graphic_eq: Arc<Mutex<GraphicEq>>,
     audio_engine: Rc<Mutex<AudioEngine>>,
+    /// Offline analysis of the whole track, used when configured instead of the
+    /// live spectrum
     spectrum: Arc<Mutex<Spectrum>>,
+    live_spectrum: Option<LiveSpectrum>,
+    /// Measures tracks without ReplayGain tags, when enabled in the config
+    loudness_scanner: Option<LoudnessScanner>,
+    /// Track appended to the engine for gapless playback, with its spectrum
+    queued_track: Option<usize>,
+    queued_spectrum: Option<Spectrum>,
+    /// Order of upcoming tracks while shuffle is on
+    shuffle: ShuffleRound,
+    /// Tracks played before the current one, for going back
+    history: PlayHistory,
+    /// How much of a track has to play before "previous" restarts it instead
+    restart_after: Duration,
     visualizer_canvas: usize,
 }
 impl AudioSystem {
     pub fn new(
         library: Arc<Mutex<MusicLibrary>>,
+        playlists: Arc<Mutex<Playlists>>,
+        play_queue: Arc<Mutex<PlayQueue>>,
         playback_state: Arc<Mutex<PlaybackState>>,
     ) -> Result<Self, Box<dyn Error>> {
-        let sound_control = Arc::new(Mutex::new(SoundControl::new()));
+        let (replay_gain_mode, loudness_scan) = get_replay_gain();
+        let restart_after = get_previous_restart();
+        let sound_control = Arc::new(Mutex::new(SoundControl::new(replay_gain_mode)));
         let spectrum = Arc::new(Mutex::new(Spectrum::default()));
         let audio_engine = Rc::new(Mutex::new(AudioEngine::new().unwrap()));
+        let spectrum_settings = get_spectrum_settings();
+        let live_spectrum = (spectrum_settings.source == SpectrumSource::Live)
+            .then(|| LiveSpectrum::new(audio_engine.lock().tap(), &spectrum_settings));
+        let graphic_eq = Arc::new(Mutex::new(GraphicEq::load()));
+        audio_engine
+            .lock()
+            .apply_graphic_eq(&graphic_eq.lock().gains);
+
+        // The playing and the next track are scanned as they come up, the rest
+        // of the library once the library scan has delivered it
+        let loudness_scanner = loudness_scan.then(LoudnessScanner::new);
 
         log_debug!("Creating new AudioSystem instance");
 
         Ok(Self {
             library,
+            playlists,
+            play_queue,
             playback_state,
             sound_control,
+            graphic_eq,
             spectrum,
+            live_spectrum,
             audio_engine,
+            loudness_scanner,
+            queued_track: None,
+            queued_spectrum: None,
+            shuffle: ShuffleRound::default(),
+            history: PlayHistory::default(),
+            restart_after,
             visualizer_canvas: 0,
         })
     }
 }
 
 impl AudioSystem {
-    /// Play a track by index
+    /// Play a track by index, as picked by the user. The track it replaces goes
+    /// into the playback history, and tracks stepped back past are forgotten.
+    /// The track is struck from the shuffle round.
     pub fn play_track(&mut self, track_index: Option<usize>) -> Result<(), Box<dyn Error>> {
         let index = match track_index {
             Some(idx) => idx,
@@ -61,7 +115,16 @@ impl AudioSystem {
                 .selected_index
                 .expect("music library must be empty"),
         };
+        self.remember_current(index);
+        self.history.clear_stepped_back();
+        if let Some(track) = self.library.lock().tracks.get(index) {
+            self.shuffle.remove(&track.path);
+        }
+        self.start_track(index)
+    }
 
+    /// Start playing a track by index
+    fn start_track(&mut self, index: usize) -> Result<(), Box<dyn Error>> {
         let track_path = {
             let library = self.library.lock();
             library
@@ -72,29 +135,30 @@ impl AudioSystem {
                 .clone()
         };
 
-        {
+        if self.live_spectrum.is_none() {
             let mut spectrum = self.spectrum.lock();
             *spectrum = Spectrum::fft_async(&track_path)
         }
+        self.queued_track = None;
+        self.queued_spectrum = None;
 
-        match self.audio_engine.lock().play(&track_path) {
+        let gain = self.replay_gain_factor(index);
+        let result = match self.crossfade_duration() {
+            Some(duration) => self
+                .audio_engine
+                .lock()
+                .crossfade(&track_path, duration, gain),
+            None => self.audio_engine.lock().play(&track_path, gain),
+        };
+        match result {
             Ok(_) => log_debug!("Now playing: {:?}", track_path),
             Err(e) => log_error!("Failed to play {:?}: {:?}", track_path, e),
         }
         // Update playback_state state
         {
-            let mut playback_state = self.playback_state.lock();
-            let duration = self
-                .library
-                .lock()
-                .tracks
-                .get(index)
-                .and_then(|track| {
-                    log_debug!("Processing track: {:?}", track);
-                    track.duration
-                })
-                .unwrap_or(Duration::ZERO);
+            let duration = self.track_duration(index);
             log_debug!("Track Duration: {:?}", duration);
+            let mut playback_state = self.playback_state.lock();
 
             playback_state.start(index, duration);
         }
@@ -111,55 +175,469 @@ impl AudioSystem {
         if self.playback_state.lock().status != PlaybackStatus::Playing {
             return;
         }
-        self.spectrum.lock().update();
-        let audio_engine = self.audio_engine.lock();
+        match self.live_spectrum.as_mut() {
+            Some(live_spectrum) => live_spectrum.update(),
+            None => self.spectrum.lock().update(),
+        }
+        if let Some(spectrum) = self.queued_spectrum.as_mut() {
+            spectrum.update();
+        }
+        if self
+            .loudness_scanner
+            .as_ref()
+            .is_some_and(|scanner| scanner.take_updated())
+        {
+            self.apply_replay_gain();
+        }
+
+        let mut audio_engine = self.audio_engine.lock();
+        if audio_engine.switch_to_queued() {
+            drop(audio_engine);
+            self.switch_to_queued_track();
+            return;
+        }
         if audio_engine.is_sink_empty() {
             drop(audio_engine);
             self.advance_track();
             return;
         }
 
-        let raw_elapsed = audio_engine.get_current_pos();
-        let speed_factor = self.get_current_speed();
-        let adjusted_elapsed = if speed_factor != 0.0 {
-            Duration::from_secs_f64(raw_elapsed.as_secs_f64() * speed_factor as f64)
-        } else {
-            raw_elapsed
+        let elapsed = audio_engine.get_current_pos();
+        drop(audio_engine);
+
+        let (remaining, total_time, crossfade) = {
+            let mut playback_state = self.playback_state.lock();
+            playback_state.update_elapsed(elapsed);
+            (
+                playback_state.total_time.saturating_sub(elapsed),
+                playback_state.total_time,
+                playback_state
+                    .crossfade_enabled
+                    .then_some(playback_state.crossfade),
+            )
+        };
+
+        match crossfade {
+            // Start the next track early enough to overlap, but never overlap more
+            // than half of a short track
+            Some(crossfade) => {
+                let tempo = self.tempo_factor();
+                let overlap = crossfade.min(total_time.div_f32(2.0 * tempo));
+                // The last track plays out in full when nothing follows it
+                if !total_time.is_zero()
+                    && remaining.div_f32(tempo) <= overlap
+                    && self.next_track_index(false).is_some()
+                {
+                    self.advance_track();
+                }
+            }
+            // Without a known duration the next track is appended right away
+            None => {
+                if self.queued_track.is_none() && remaining <= PRELOAD_AHEAD {
+                    self.preload_next_track();
+                }
+            }
+        }
+    }
+
+    /// How long to crossfade into a new track, None to switch right away. The fade
+    /// never outlasts what is left of the playing track.
+    fn crossfade_duration(&self) -> Option<Duration> {
+        let playback_state = self.playback_state.lock();
+        if !playback_state.crossfade_enabled || playback_state.status != PlaybackStatus::Playing {
+            return None;
+        }
+        if playback_state.total_time.is_zero() {
+            return Some(playback_state.crossfade);
+        }
+        let remaining = playback_state
+            .total_time
+            .saturating_sub(playback_state.elapsed)
+            .div_f32(self.tempo_factor());
+        Some(playback_state.crossfade.min(remaining))
+    }
+
+    /// ReplayGain factor of a track in the current mode, from its tags or else from
+    /// a loudness scan. Tracks not scanned yet play unchanged and get scanned next.
+    fn replay_gain_factor(&self, index: usize) -> f32 {
+        let mode = self.sound_control.lock().replay_gain_mode();
+        let Some((path, mut replay_gain)) = self
+            .library
+            .lock()
+            .tracks
+            .get(index)
+            .map(|track| (track.path.clone(), track.replay_gain))
+        else {
+            return 1.0;
+        };
+
+        if !replay_gain.has_track_gain() {
+            if let Some(scanner) = &self.loudness_scanner {
+                match scanner.get(&path) {
+                    Some(loudness) => replay_gain = loudness.replay_gain(),
+                    None => scanner.request(&path, true),
+                }
+            }
+        }
+        replay_gain.factor(mode)
+    }
+
+    /// Recompute the ReplayGain of the playing and queued tracks
+    fn apply_replay_gain(&mut self) {
+        let Some(current) = self.playback_state.lock().current_track else {
+            return;
+        };
+        let current = self.replay_gain_factor(current);
+        let queued = self
+            .queued_track
+            .map(|index| self.replay_gain_factor(index));
+        self.audio_engine.lock().set_replay_gain(current, queued);
+    }
+
+    fn tempo_factor(&self) -> f32 {
+        (self.sound_control.lock().tempo() / 100.0).max(0.01)
+    }
+
+    /// Decode the track that follows the current one and append it to the engine
+    fn preload_next_track(&mut self) {
+        let Some(next_index) = self.next_track_index(false) else {
+            return;
+        };
+        let Some(track_path) = self
+            .library
+            .lock()
+            .tracks
+            .get(next_index)
+            .map(|track| track.path.clone())
+        else {
+            return;
+        };
+
+        // Remembered even on failure so the attempt is not repeated every frame;
+        // advance_track takes over once the current track has ended
+        self.queued_track = Some(next_index);
+        let gain = self.replay_gain_factor(next_index);
+        match self.audio_engine.lock().enqueue(&track_path, gain) {
+            Ok(_) => {
+                if self.live_spectrum.is_none() {
+                    self.queued_spectrum = Some(Spectrum::fft_async(&track_path));
+                }
+                log_debug!("Queued for gapless playback: {:?}", track_path);
+            }
+            Err(e) => log_error!("Failed to queue {:?}: {:?}", track_path, e),
+        }
+    }
+
+    /// Playback crossed over to the queued track, follow it with the playback state
+    /// and the spectrum
+    fn switch_to_queued_track(&mut self) {
+        let Some(index) = self.queued_track.take() else {
+            return;
+        };
+        if let Some(spectrum) = self.queued_spectrum.take() {
+            *self.spectrum.lock() = spectrum;
+        }
+
+        let duration = self.track_duration(index);
+        self.follow_next_track(index, false);
+        self.remember_current(index);
+        self.library.lock().selected_index = Some(index);
+        self.playback_state.lock().start(index, duration);
+        log_debug!("Gapless switch to track {}", index);
+    }
+
+    /// Take over the result of a library scan or watcher update, keeping the playing
+    /// and queued tracks pointed at the same files
+    pub fn refresh_library(&mut self) {
+        let remap = {
+            let mut library = self.library.lock();
+            let Some(tracks) = library.take_update() else {
+                return;
+            };
+            library.replace_tracks(tracks)
+        };
+        self.follow_moved_tracks(&remap);
+
+        if let Some(scanner) = &self.loudness_scanner {
+            for track in self.library.lock().tracks.iter() {
+                if !track.replay_gain.has_track_gain() {
+                    scanner.request(&track.path, false);
+                }
+            }
+        }
+        log_debug!("Library refreshed");
+    }
+
+    /// Point the playing and the upcoming track at their new index after the
+    /// library's tracks were replaced or reordered
+    fn follow_moved_tracks(&mut self, remap: &[Option<usize>]) {
+        let moved = |index: Option<usize>| index.and_then(|i| remap.get(i).copied().flatten());
+
+        {
+            let mut playback_state = self.playback_state.lock();
+            playback_state.current_track = moved(playback_state.current_track);
+        }
+        if self.queued_track.is_some() {
+            self.queued_track = moved(self.queued_track);
+            if self.queued_track.is_none() {
+                self.audio_engine.lock().cancel_queued();
+                self.queued_spectrum = None;
+            }
+        }
+    }
+
+    /// Index of the track that plays after the current one: the first track of
+    /// the play queue, then the current track again when repeating one, then
+    /// the track stepped back past, then the next track of the shuffle round,
+    /// the playing playlist or the library. A `skip` moves on even when
+    /// repeating one. None once the end is reached and playback does not repeat.
+    fn next_track_index(&mut self, skip: bool) -> Option<usize> {
+        {
+            let library = self.library.lock();
+            if let Some((_, index)) = self.play_queue.lock().next(&library) {
+                return Some(index);
+            }
+        }
+
+        let (repeat, shuffle, current_track) = {
+            let playback_state = self.playback_state.lock();
+            (
+                playback_state.repeat,
+                playback_state.shuffle,
+                playback_state.current_track,
+            )
+        };
+        let wrap = repeat == RepeatMode::All;
+        if repeat == RepeatMode::One && current_track.is_some() && !skip {
+            return current_track;
+        }
+        if let Some(index) = self.history.next(&self.library.lock()) {
+            return Some(index);
+        }
+        if shuffle != ShuffleMode::Off {
+            return self.next_shuffled_index(shuffle, wrap, current_track);
+        }
+        if self.playlists.lock().playing.is_some() {
+            let library = self.library.lock();
+            return self
+                .playlists
+                .lock()
+                .next_entry(&library, wrap)
+                .map(|(_, index)| index);
+        }
+
+        let track_count = self.library.lock().tracks.len();
+        match current_track {
+            Some(current_index) if current_index + 1 < track_count => Some(current_index + 1),
+            Some(_) if wrap && track_count > 0 => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Next track of the shuffle round over the playing playlist or the
+    /// library, drawing a new round when there is none yet or, if playback
+    /// repeats, when the round is over
+    fn next_shuffled_index(
+        &mut self,
+        mode: ShuffleMode,
+        wrap: bool,
+        current_track: Option<usize>,
+    ) -> Option<usize> {
+        let library = self.library.lock();
+        let playlists = self.playlists.lock();
+        let source = playlists.playing.map(|(playlist, _)| playlist);
+        let tracks = || match source.and_then(|playlist| playlists.playlists.get(playlist)) {
+            Some(playlist) => playlist
+                .entries
+                .iter()
+                .filter_map(|entry| library.index_of(&entry.path))
+                .map(|index| &library.tracks[index])
+                .collect(),
+            None => library.tracks.iter().collect(),
+        };
+        let current = current_track.and_then(|index| library.tracks.get(index));
+
+        if !self.shuffle.is_drawn_from(source, mode) {
+            self.shuffle.draw(source, mode, tracks(), current);
+        }
+        match self.shuffle.next(&library) {
+            Some(index) => Some(index),
+            None if wrap => {
+                self.shuffle.draw(source, mode, tracks(), current);
+                self.shuffle.next(&library)
+            }
+            None => None,
+        }
+    }
+
+    fn track_duration(&self, index: usize) -> Duration {
+        self.library
+            .lock()
+            .tracks
+            .get(index)
+            .and_then(|track| track.duration)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Move the play queue, the playback history, the shuffle round or the
+    /// playing playlist on past the track `next_track_index` chose, once it
+    /// starts. A track from the queue or the history is struck from the
+    /// shuffle round, so it does not play again before the round is over.
+    fn follow_next_track(&mut self, index: usize, skip: bool) {
+        let library = self.library.lock();
+        if self.play_queue.lock().take(&library, index) {
+            if let Some(track) = library.tracks.get(index) {
+                self.shuffle.remove(&track.path);
+            }
+            return;
+        }
+        if self.history.take(&library, index) {
+            if let Some(track) = library.tracks.get(index) {
+                self.shuffle.remove(&track.path);
+                self.playlists.lock().follow_path(&track.path);
+            }
+            return;
+        }
+
+        let (repeat, shuffle) = {
+            let playback_state = self.playback_state.lock();
+            (playback_state.repeat, playback_state.shuffle)
         };
+        match (repeat, shuffle) {
+            (RepeatMode::One, _) if !skip => {}
+            (_, ShuffleMode::Off) => {
+                drop(library);
+                self.follow_playlist(repeat == RepeatMode::All);
+            }
+            _ => {
+                self.shuffle.take(&library, index);
+                if let Some(track) = library.tracks.get(index) {
+                    self.playlists.lock().follow_path(&track.path);
+                }
+            }
+        }
+    }
 
-        self.playback_state.lock().update_elapsed(adjusted_elapsed);
+    /// Move the playing playlist on to the entry `next_track_index` chose
+    fn follow_playlist(&self, wrap: bool) {
+        let library = self.library.lock();
+        let mut playlists = self.playlists.lock();
+        if let Some((entry, _)) = playlists.next_entry(&library, wrap) {
+            if let Some((_, playing_entry)) = playlists.playing.as_mut() {
+                *playing_entry = entry;
+            }
+        }
     }
 
     /// Advance to the next track automatically
     fn advance_track(&mut self) {
-        let mut library = self.library.lock();
-        let current_track = {
+        if !self.start_next_track(false) {
+            // Nothing left to play without repeating
+            self.stop();
+            log_debug!("Reached the end of the list, playback stopped");
+        }
+    }
+
+    /// Skip to the track that plays next, moving on even when repeating one.
+    /// Nothing happens at the end of the list.
+    pub fn next_track(&mut self) {
+        if !self.start_next_track(true) {
+            log_debug!("No track to skip to");
+        }
+    }
+
+    /// Start the track that follows the current one, false when there is none
+    fn start_next_track(&mut self, skip: bool) -> bool {
+        let Some(next_index) = self.next_track_index(skip) else {
+            return false;
+        };
+        self.follow_next_track(next_index, skip);
+        self.remember_current(next_index);
+        self.library.lock().selected_index = Some(next_index);
+        match self.start_track(next_index) {
+            Ok(_) => {
+                log_debug!("Successfully advanced to next track");
+            }
+            Err(e) => {
+                log_error!("Failed to advance to next track: {}", e);
+            }
+        }
+        true
+    }
+
+    /// Go back to the track that played before the current one. Once more
+    /// than `restart_after` of the current track has played, or when there is
+    /// nothing to go back to, the current track starts over instead.
+    pub fn previous_track(&mut self) -> Result<(), Box<dyn Error>> {
+        let (current_track, elapsed) = {
             let playback_state = self.playback_state.lock();
-            playback_state.current_track
+            (playback_state.current_track, playback_state.elapsed)
         };
+        let Some(current) = current_track else {
+            return Ok(());
+        };
+        // A threshold of zero always goes back
+        if !self.restart_after.is_zero() && elapsed > self.restart_after {
+            return self.restart_track(current);
+        }
 
-        if let Some(current_index) = current_track {
-            let next_index = (current_index + 1) % library.tracks.len();
-            library.selected_index = Some(next_index);
-            drop(library);
+        let previous = {
+            let library = self.library.lock();
+            let current_path = library.tracks.get(current).map(|track| track.path.clone());
+            self.history
+                .step_back(&library, current_path.as_deref())
+                .map(|index| (index, library.tracks[index].path.clone()))
+        };
+        match previous {
+            Some((index, path)) => {
+                self.playlists.lock().follow_path(&path);
+                self.library.lock().selected_index = Some(index);
+                log_debug!("Back to {:?}", path);
+                self.start_track(index)
+            }
+            None => self.restart_track(current),
+        }
+    }
 
-            match self.play_track(Some(next_index)) {
-                Ok(_) => {
-                    log_debug!("Successfully advanced to next track");
-                }
-                Err(e) => {
-                    log_error!("Failed to advance to next track: {}", e);
-                }
+    /// Play the current track again from the start
+    fn restart_track(&mut self, index: usize) -> Result<(), Box<dyn Error>> {
+        if self.playback_state.lock().status == PlaybackStatus::Stopped {
+            return self.start_track(index);
+        }
+        let result = self.audio_engine.lock().seek_control(Duration::ZERO);
+        match result {
+            Ok(_) => {
+                self.playback_state.lock().update_elapsed(Duration::ZERO);
+                log_debug!("Restarted track {}", index);
+                Ok(())
+            }
+            // Seeking does not work for every format; decode the track anew
+            Err(e) => {
+                log_debug!("Cannot seek to the start, restarting the track: {}", e);
+                self.start_track(index)
             }
-        } else {
-            log_error!("Cannot advance track: no current track");
+        }
+    }
+
+    /// Put the current track into the playback history before another one
+    /// replaces it
+    fn remember_current(&mut self, next_index: usize) {
+        let current_track = self.playback_state.lock().current_track;
+        let library = self.library.lock();
+        if let Some(track) = current_track
+            .filter(|&current| current != next_index)
+            .and_then(|current| library.tracks.get(current))
+        {
+            self.history.push(&track.path);
         }
     }
 
     /// Apply current sound_control settings to audio output
     fn apply_sound_settings(&mut self) {
         let sound_control = self.sound_control.lock();
-        self.audio_engine.lock().apply_effects(&sound_control);
+        let mut audio_engine = self.audio_engine.lock();
+        audio_engine.apply_effects(&sound_control);
+        audio_engine.apply_graphic_eq(&self.graphic_eq.lock().gains);
     }
 
     /// Toggle playback_state between play and pause
@@ -211,10 +689,22 @@ impl AudioSystem {
 }
 
 impl AudioSystem {
-    pub fn get_current_frame(&self) -> Vec<f32> {
+    /// Why the offline analysis of the playing track failed, if it did
+    pub fn get_spectrum_error(&self) -> Option<String> {
+        if self.live_spectrum.is_some() {
+            return None;
+        }
+        self.spectrum.lock().error.clone()
+    }
+
+    pub fn get_current_frame(&self) -> SpectrumFrame {
+        if let Some(live_spectrum) = &self.live_spectrum {
+            return live_spectrum.frame();
+        }
+
         let spectrum = self.spectrum.lock();
         if spectrum.processing || spectrum.size == 0 || spectrum.inner.is_empty() {
-            return vec![];
+            return SpectrumFrame::default();
         }
 
         let elapsed = self.playback_state.lock().elapsed.as_millis() as usize;
@@ -224,11 +714,14 @@ impl AudioSystem {
 
         // Ensure bounds safety
         if ptr + spectrum.size > spectrum.inner.len() {
-            return vec![];
+            return SpectrumFrame::default();
         }
 
         // Copy the current frame into a new vector
-        spectrum.inner[ptr..ptr + spectrum.size].to_vec()
+        SpectrumFrame {
+            magnitudes: spectrum.inner[ptr..ptr + spectrum.size].to_vec(),
+            sample_rate: spectrum.sample_rate,
+        }
     }
 }
 
@@ -253,6 +746,9 @@ impl AudioSystem {
         playback_state.status = PlaybackStatus::Stopped;
         playback_state.elapsed = Duration::ZERO;
         self.audio_engine.lock().stop();
+        if let Some(live_spectrum) = self.live_spectrum.as_mut() {
+            live_spectrum.clear();
+        }
     }
 }
 
@@ -288,6 +784,135 @@ impl AudioSystem {
         }
         self.apply_sound_settings();
     }
+
+    pub fn adjust_tempo(&mut self, delta: f32) {
+        {
+            let mut sound_control = self.sound_control.lock();
+            sound_control.adjust_tempo(delta);
+        }
+        self.apply_sound_settings();
+    }
+
+    pub fn adjust_eq_band(&mut self, delta: f32) {
+        self.graphic_eq.lock().adjust_band(delta);
+        self.apply_sound_settings();
+        self.save_graphic_eq();
+    }
+
+    pub fn cycle_eq_preset(&mut self, step: isize) {
+        self.graphic_eq.lock().cycle_preset(step);
+        self.apply_sound_settings();
+        self.save_graphic_eq();
+    }
+
+    pub fn reset_eq(&mut self) {
+        self.graphic_eq.lock().reset();
+        self.apply_sound_settings();
+        self.save_graphic_eq();
+    }
+
+    pub fn save_eq_preset(&mut self, name: &str) {
+        {
+            let mut graphic_eq = self.graphic_eq.lock();
+            let name = graphic_eq.save_as_user_preset(name);
+            log_debug!("Saved equalizer preset {}", name);
+        }
+        self.save_graphic_eq();
+    }
+
+    pub fn delete_eq_preset(&mut self) {
+        if self.graphic_eq.lock().delete_user_preset() {
+            self.apply_sound_settings();
+            self.save_graphic_eq();
+        }
+    }
+
+    fn save_graphic_eq(&self) {
+        if let Err(e) = self.graphic_eq.lock().save() {
+            log_error!("Failed to save equalizer state: {}", e);
+        }
+    }
+
+    pub fn cycle_replay_gain_mode(&mut self) {
+        self.sound_control.lock().cycle_replay_gain_mode();
+        self.apply_replay_gain();
+    }
+
+    pub fn toggle_crossfade(&mut self) {
+        let enabled = {
+            let mut playback_state = self.playback_state.lock();
+            playback_state.crossfade_enabled = !playback_state.crossfade_enabled;
+            playback_state.crossfade_enabled
+        };
+
+        // A track appended for gapless playback would play without a fade
+        if enabled {
+            let mut audio_engine = self.audio_engine.lock();
+            audio_engine.cancel_queued();
+            if !audio_engine.has_queued() {
+                self.queued_track = None;
+                self.queued_spectrum = None;
+            }
+        }
+        log_debug!("Crossfade {}", if enabled { "enabled" } else { "disabled" });
+    }
+
+    /// Switch to the next repeat mode: off, one, all
+    pub fn cycle_repeat_mode(&mut self) {
+        let repeat = {
+            let mut playback_state = self.playback_state.lock();
+            playback_state.repeat = playback_state.repeat.next();
+            playback_state.repeat
+        };
+        self.refresh_upcoming();
+        log_debug!("Repeat mode: {}", repeat.label());
+    }
+
+    /// Switch to the next shuffle mode: off, on, spread artists. Every change
+    /// starts a fresh shuffle round.
+    pub fn cycle_shuffle_mode(&mut self) {
+        let shuffle = {
+            let mut playback_state = self.playback_state.lock();
+            playback_state.shuffle = playback_state.shuffle.next();
+            playback_state.shuffle
+        };
+        self.shuffle.reset();
+        self.refresh_upcoming();
+        log_debug!("Shuffle mode: {}", shuffle.label());
+    }
+
+    /// Sort the library in its next order: file name, artist, album, track
+    /// number, duration, date added. The playing track keeps playing and
+    /// playback goes on in the new order.
+    pub fn cycle_library_sort(&mut self) {
+        let (sort, remap) = {
+            let mut library = self.library.lock();
+            let sort = library.sort.next();
+            (sort, library.set_sort(sort))
+        };
+        self.follow_moved_tracks(&remap);
+        self.refresh_upcoming();
+        log_debug!("Library sorted by {}", sort.label());
+    }
+
+    /// Drop the track decoded ahead for gapless playback when the play queue,
+    /// the play order or the playing playlist no longer leads to it
+    pub fn refresh_upcoming(&mut self) {
+        if self.queued_track.is_none() {
+            return;
+        }
+        let next_index = self.next_track_index(false);
+        if self.queued_track == next_index {
+            return;
+        }
+        let mut audio_engine = self.audio_engine.lock();
+        audio_engine.cancel_queued();
+        if !audio_engine.has_queued() {
+            self.queued_track = None;
+            self.queued_spectrum = None;
+        }
+    }
+
     pub fn set_visualizer_canvas_type(&mut self, canvas_type: usize) {
         if canvas_type <= 9 {
             self.visualizer_canvas = canvas_type;
@@ -298,6 +923,10 @@ impl AudioSystem {
         Arc::clone(&self.sound_control)
     }
 
+    pub fn get_graphic_eq_state(&self) -> Arc<Mutex<GraphicEq>> {
+        Arc::clone(&self.graphic_eq)
+    }
+
     pub fn get_visualizer_canvas_type(&self) -> usize {
         self.visualizer_canvas
     }
@@ -317,14 +946,10 @@ impl AudioSystem {
 
         match self.audio_engine.lock().seek_control(new_audio_position) {
             Ok(_) => {
-                // Update the elapsed time based on the current speed factor
-                let speed_factor = self.get_current_speed();
-                let new_playback_position =
-                    Duration::from_secs_f64(new_audio_position.as_secs_f64() * speed_factor as f64);
                 self.playback_state
                     .lock()
-                    .update_elapsed(new_playback_position);
-                log_debug!("Successfully sought forward to {:?}", new_playback_position);
+                    .update_elapsed(new_audio_position);
+                log_debug!("Successfully sought forward to {:?}", new_audio_position);
             }
             Err(e) => log_error!("Failed to seek forward: {}", e),
         };
@@ -344,23 +969,12 @@ impl AudioSystem {
 
         match self.audio_engine.lock().seek_control(new_audio_position) {
             Ok(_) => {
-                // Update the elapsed time based on the current speed factor
-                let speed_factor = self.get_current_speed();
-                let new_playback_position =
-                    Duration::from_secs_f64(new_audio_position.as_secs_f64() * speed_factor as f64);
                 self.playback_state
                     .lock()
-                    .update_elapsed(new_playback_position);
-                log_debug!(
-                    "Successfully sought backward to {:?}",
-                    new_playback_position
-                );
+                    .update_elapsed(new_audio_position);
+                log_debug!("Successfully sought backward to {:?}", new_audio_position);
             }
             Err(e) => log_error!("Failed to seek backward: {}", e),
         };
     }
-    pub fn get_current_speed(&self) -> f32 {
-        let pitch = self.sound_control.lock().pitch();
-        AudioEngine::calc_playback_speed(pitch)
-    }
 }
@@ -1,8 +1,26 @@
 use clap::Parser;
 use directories::{ProjectDirs, UserDirs};
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::controls::band_mapping::BandSettings;
+use crate::controls::library_roots::LibraryRoots;
+use crate::controls::music_library::LibrarySort;
+use crate::controls::native_bars::BarSettings;
+use crate::controls::playback_state::{RepeatMode, ShuffleMode};
+use crate::controls::replay_gain::ReplayGainMode;
+use crate::controls::spectrum::SpectrumSettings;
+use crate::log_error;
+
+/// Crossfade length used when the config file does not set one
+const DEFAULT_CROSSFADE_SECONDS: f32 = 5.0;
+
+/// How far into a track "previous" restarts it, when the config file does not
+/// say
+const DEFAULT_PREVIOUS_RESTART_SECONDS: f32 = 3.0;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -12,65 +30,306 @@ use std::path::PathBuf;
     about = "A terminal-based music player with a clean TUI"
 )]
 pub struct Args {
-    /// Path to the music directory
-    #[arg(short, long)]
-    music_dir: Option<PathBuf>,
+    /// Path to a music directory (can be given several times)
+    #[arg(short, long = "music-dir", value_name = "DIR")]
+    music_dirs: Vec<PathBuf>,
+
+    /// Import an M3U/M3U8 or PLS playlist (can be given several times)
+    #[arg(long = "import-playlist", value_name = "FILE")]
+    import_playlists: Vec<PathBuf>,
 }
 
 #[derive(Deserialize, Debug)]
 struct Config {
+    #[serde(default, deserialize_with = "lenient")]
     music_dir: Option<String>,
+    /// Further music directories, merged into one library
+    #[serde(default, deserialize_with = "lenient")]
+    music_dirs: Option<Vec<String>>,
+    /// Glob patterns of files and folders to leave out of the library
+    #[serde(default, deserialize_with = "lenient")]
+    exclude: Option<Vec<String>>,
+    /// Whether scans follow symlinks
+    #[serde(default, deserialize_with = "lenient")]
+    follow_symlinks: Option<bool>,
+    /// How many folder levels below a music directory are scanned
+    #[serde(default, deserialize_with = "lenient")]
+    max_depth: Option<usize>,
+    /// Order of the track list at startup: "file_name", "artist", "album",
+    /// "track_number", "duration" or "date_added"
+    #[serde(default, deserialize_with = "lenient")]
+    sort: Option<String>,
+    /// Whether crossfading is switched on at startup
+    #[serde(default, deserialize_with = "lenient")]
+    crossfade: Option<bool>,
+    /// How long the end of one track overlaps the start of the next
+    #[serde(default, deserialize_with = "lenient")]
+    crossfade_seconds: Option<f32>,
+    /// ReplayGain mode at startup: "track", "album" or "off"
+    #[serde(default, deserialize_with = "lenient")]
+    replay_gain: Option<String>,
+    /// Measure the loudness of files without ReplayGain tags
+    #[serde(default, deserialize_with = "lenient")]
+    loudness_scan: Option<bool>,
+    /// Repeat mode at startup: "off", "one" or "all"
+    #[serde(default, deserialize_with = "lenient")]
+    repeat: Option<String>,
+    /// Shuffle mode at startup: "off", "on" or "spread_artists"
+    #[serde(default, deserialize_with = "lenient")]
+    shuffle: Option<String>,
+    /// Seconds a track has to play before "previous" restarts it instead of
+    /// going back
+    #[serde(default, deserialize_with = "lenient")]
+    previous_restart_seconds: Option<f32>,
+    /// How the visualizer's spectrum is computed
+    #[serde(default, deserialize_with = "lenient")]
+    spectrum: Option<SpectrumSettings>,
+    /// How the spectrum is divided into visualizer bands
+    #[serde(default, deserialize_with = "lenient")]
+    bands: Option<BandSettings>,
+    /// Look of the bar visualizer
+    #[serde(default, deserialize_with = "lenient")]
+    bars: Option<BarSettings>,
+}
+
+/// Directory holding the config file and other persisted settings
+pub fn get_config_dir() -> Option<PathBuf> {
+    ProjectDirs::from("com", "", "melovitui").map(|proj_dirs| proj_dirs.config_dir().to_path_buf())
+}
+
+/// Directory for persistent application data, such as the library index
+pub fn get_data_dir() -> Option<PathBuf> {
+    ProjectDirs::from("com", "", "melovitui").map(|proj_dirs| proj_dirs.data_dir().to_path_buf())
+}
+
+/// Directory for data that can be recomputed, such as loudness scans
+pub fn get_cache_dir() -> Option<PathBuf> {
+    ProjectDirs::from("com", "", "melovitui").map(|proj_dirs| proj_dirs.cache_dir().to_path_buf())
 }
 
 fn get_config_path() -> Option<PathBuf> {
-    ProjectDirs::from("com", "", "melovitui").map(|proj_dirs| {
-        let config_dir = proj_dirs.config_dir();
-        config_dir.join("config.json")
-    })
+    get_config_dir().map(|config_dir| config_dir.join("config.json"))
 }
 
-pub fn get_music_dir() -> PathBuf {
-    let args = Args::parse();
+fn read_config() -> Option<Config> {
+    let config_str = fs::read_to_string(get_config_path()?).ok()?;
+    match serde_json::from_str::<Config>(&config_str) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            log_error!("Ignoring malformed config file: {}", e);
+            None
+        }
+    }
+}
 
-    // Try command line argument first
-    if let Some(dir) = args.music_dir {
-        if dir.exists() {
-            return dir;
+/// Read an optional setting, leaving it unset when its value does not fit
+/// instead of rejecting the whole config file
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    if value.is_null() {
+        return Ok(None);
+    }
+    match serde_json::from_value(value.clone()) {
+        Ok(setting) => Ok(Some(setting)),
+        Err(e) => {
+            log_error!("Ignoring invalid config value {}: {}", value, e);
+            Ok(None)
         }
-        eprintln!(
-            "Warning: Specified music directory does not exist: {:?}",
-            dir
-        );
+    }
+}
+
+/// Whether crossfading starts enabled, and for how long tracks overlap
+pub fn get_crossfade() -> (bool, Duration) {
+    let config = read_config();
+    let enabled = config
+        .as_ref()
+        .and_then(|config| config.crossfade)
+        .unwrap_or(false);
+    let seconds = config
+        .and_then(|config| config.crossfade_seconds)
+        .unwrap_or(DEFAULT_CROSSFADE_SECONDS)
+        .clamp(0.5, 30.0);
+    (enabled, Duration::from_secs_f32(seconds))
+}
+
+/// The ReplayGain mode to start with, and whether untagged files get scanned
+pub fn get_replay_gain() -> (ReplayGainMode, bool) {
+    let config = read_config();
+    let mode = config
+        .as_ref()
+        .and_then(|config| config.replay_gain.as_deref())
+        .and_then(ReplayGainMode::from_name)
+        .unwrap_or_default();
+    let loudness_scan = config
+        .and_then(|config| config.loudness_scan)
+        .unwrap_or(false);
+    (mode, loudness_scan)
+}
+
+/// The repeat and shuffle modes to start with
+pub fn get_play_order() -> (RepeatMode, ShuffleMode) {
+    let config = read_config();
+    let repeat = config
+        .as_ref()
+        .and_then(|config| config.repeat.as_deref())
+        .and_then(RepeatMode::from_name)
+        .unwrap_or_default();
+    let shuffle = config
+        .as_ref()
+        .and_then(|config| config.shuffle.as_deref())
+        .and_then(ShuffleMode::from_name)
+        .unwrap_or_default();
+    (repeat, shuffle)
+}
+
+/// The order to sort the track list in at startup
+pub fn get_library_sort() -> LibrarySort {
+    read_config()
+        .and_then(|config| config.sort)
+        .as_deref()
+        .and_then(LibrarySort::from_name)
+        .unwrap_or_default()
+}
+
+/// How much of a track has to play before "previous" restarts it
+pub fn get_previous_restart() -> Duration {
+    let seconds = read_config()
+        .and_then(|config| config.previous_restart_seconds)
+        .unwrap_or(DEFAULT_PREVIOUS_RESTART_SECONDS)
+        .clamp(0.0, 60.0);
+    Duration::from_secs_f32(seconds)
+}
+
+/// Spectrum analysis settings, defaults for anything the config leaves out
+pub fn get_spectrum_settings() -> SpectrumSettings {
+    read_config()
+        .and_then(|config| config.spectrum)
+        .unwrap_or_default()
+}
+
+/// Visualizer band settings, defaults for anything the config leaves out
+pub fn get_band_settings() -> BandSettings {
+    read_config()
+        .and_then(|config| config.bands)
+        .unwrap_or_default()
+}
+
+/// Bar visualizer settings, defaults for anything the config leaves out
+pub fn get_bar_settings() -> BarSettings {
+    read_config()
+        .and_then(|config| config.bars)
+        .unwrap_or_default()
+}
+
+/// Playlist files to import on startup, from the command line
+pub fn get_playlist_imports() -> Vec<PathBuf> {
+    Args::parse().import_playlists
+}
+
+/// The music directories to build the library from, with the exclude
+/// patterns, symlink following and depth limit from the config
+pub fn get_library_roots() -> LibraryRoots {
+    let config = read_config();
+    let dirs = get_music_dirs(config.as_ref());
+    let Some(config) = config else {
+        return LibraryRoots::new(dirs, &[], false, None);
+    };
+    LibraryRoots::new(
+        dirs,
+        config.exclude.as_deref().unwrap_or_default(),
+        config.follow_symlinks.unwrap_or(false),
+        config.max_depth,
+    )
+}
+
+fn get_music_dirs(config: Option<&Config>) -> Vec<PathBuf> {
+    let args = Args::parse();
+
+    // Try command line arguments first
+    let dirs = existing_dirs(args.music_dirs, "Specified music directory");
+    if !dirs.is_empty() {
+        return dirs;
     }
 
     // Try config file
-    if let Some(config_path) = get_config_path() {
-        if let Ok(config_str) = fs::read_to_string(config_path) {
-            if let Ok(config) = serde_json::from_str::<Config>(&config_str) {
-                if let Some(dir_str) = config.music_dir {
-                    let dir = PathBuf::from(dir_str);
-                    if dir.exists() {
-                        return dir;
-                    }
-                    eprintln!(
-                        "Warning: Music directory from config does not exist: {:?}",
-                        dir
-                    );
-                }
-            }
-        }
+    let configured = config
+        .into_iter()
+        .flat_map(|config| {
+            config
+                .music_dir
+                .iter()
+                .chain(config.music_dirs.iter().flatten())
+        })
+        .map(PathBuf::from)
+        .collect();
+    let dirs = existing_dirs(configured, "Music directory from config");
+    if !dirs.is_empty() {
+        return dirs;
     }
 
     // Try user's Music directory
     if let Some(user_dirs) = UserDirs::new() {
         if let Some(music_dir) = user_dirs.audio_dir() {
             if music_dir.exists() {
-                return music_dir.to_path_buf();
+                return vec![music_dir.to_path_buf()];
             }
         }
     }
 
     // Fallback to current directory
     eprintln!("Warning: Falling back to current directory");
-    PathBuf::from(".")
+    vec![PathBuf::from(".")]
+}
+
+/// The directories that exist, warning about the others. A directory given
+/// twice, or lying below another one, is only kept once so its files are not
+/// scanned twice; paths are compared in canonical form to catch the same
+/// directory spelled differently.
+fn existing_dirs(dirs: Vec<PathBuf>, source: &str) -> Vec<PathBuf> {
+    let mut existing: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for dir in dirs {
+        match fs::canonicalize(&dir) {
+            Ok(canonical) => existing.push((dir, canonical)),
+            Err(_) => eprintln!("Warning: {} does not exist: {:?}", source, dir),
+        }
+    }
+
+    let mut kept: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (dir, canonical) in &existing {
+        let nested = existing
+            .iter()
+            .any(|(_, outer)| outer != canonical && canonical.starts_with(outer));
+        if nested {
+            eprintln!("Warning: {:?} lies below another music directory", dir);
+        } else if !kept.iter().any(|(_, other)| other == canonical) {
+            kept.push((dir.clone(), canonical.clone()));
+        }
+    }
+    kept.into_iter().map(|(dir, _)| dir).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_value_leaves_other_settings_intact() {
+        let config: Config =
+            serde_json::from_str(r#"{"crossfade": "yes", "repeat": "all", "max_depth": -1}"#)
+                .unwrap();
+        assert_eq!(config.crossfade, None);
+        assert_eq!(config.max_depth, None);
+        assert_eq!(config.repeat.as_deref(), Some("all"));
+    }
+
+    #[test]
+    fn missing_and_null_values_are_unset() {
+        let config: Config = serde_json::from_str(r#"{"shuffle": null}"#).unwrap();
+        assert_eq!(config.shuffle, None);
+        assert_eq!(config.sort, None);
+    }
 }
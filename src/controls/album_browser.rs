@@ -0,0 +1,311 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+use crate::controls::music_library::{MusicLibrary, Track};
+
+/// A column of the album browser
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BrowserColumn {
+    #[default]
+    Artists,
+    Albums,
+    Tracks,
+}
+
+/// The tracks of one album, in disc and track order
+#[derive(Debug, Clone)]
+pub struct AlbumGroup {
+    pub title: String,
+    /// Earliest year among the album's tracks
+    pub year: Option<i32>,
+    /// Library indices of the tracks
+    pub tracks: Vec<usize>,
+    /// Whether the tracks come from more than one disc
+    pub multi_disc: bool,
+    key: String,
+    /// Paths of the tracks, to find them again after the library changed
+    paths: Vec<PathBuf>,
+}
+
+/// The albums of one album artist, oldest first
+#[derive(Debug, Clone)]
+pub struct ArtistGroup {
+    pub name: String,
+    pub albums: Vec<AlbumGroup>,
+    key: String,
+}
+
+/// Tracks grouped by their tags into album artists, their albums and the
+/// albums' tracks, browsed in three columns side by side. Tracks without an
+/// album artist go by their artist. The groups refer to tracks by their index
+/// in the library and are rebuilt when it changes.
+#[derive(Debug, Default)]
+pub struct AlbumBrowser {
+    pub artists: Vec<ArtistGroup>,
+    /// Column the cursor is in
+    pub column: BrowserColumn,
+    /// Cursor rows in the artist, album and track columns
+    pub selected: [usize; 3],
+    /// Library revision the groups were built from
+    revision: u64,
+}
+
+impl AlbumBrowser {
+    /// Regroup the tracks when the library has changed, keeping the cursor on
+    /// the same artist, album and track where they still exist
+    pub fn refresh(&mut self, library: &MusicLibrary) {
+        if self.revision == library.revision {
+            return;
+        }
+        self.revision = library.revision;
+        let artist_key = self.artist().map(|artist| artist.key.clone());
+        let album_key = self.album().map(|album| album.key.clone());
+        let track_path = self
+            .album()
+            .and_then(|album| album.paths.get(self.selected[2]))
+            .cloned();
+
+        self.artists = group_tracks(&library.tracks);
+
+        self.selected = [0; 3];
+        let Some(artist) =
+            artist_key.and_then(|key| self.artists.iter().position(|a| a.key == key))
+        else {
+            self.column = BrowserColumn::Artists;
+            return;
+        };
+        self.selected[0] = artist;
+        let albums = &self.artists[artist].albums;
+        let Some(album) = album_key.and_then(|key| albums.iter().position(|a| a.key == key)) else {
+            if self.column == BrowserColumn::Tracks {
+                self.column = BrowserColumn::Albums;
+            }
+            return;
+        };
+        self.selected[1] = album;
+        if let Some(track) =
+            track_path.and_then(|path| albums[album].paths.iter().position(|p| *p == path))
+        {
+            self.selected[2] = track;
+        }
+    }
+
+    /// The artist under or left of the cursor
+    pub fn artist(&self) -> Option<&ArtistGroup> {
+        self.artists.get(self.selected[0])
+    }
+
+    /// The album under or left of the cursor
+    pub fn album(&self) -> Option<&AlbumGroup> {
+        self.artist()?.albums.get(self.selected[1])
+    }
+
+    /// Number of rows in the column the cursor is in
+    pub fn column_len(&self) -> usize {
+        match self.column {
+            BrowserColumn::Artists => self.artists.len(),
+            BrowserColumn::Albums => self.artist().map_or(0, |artist| artist.albums.len()),
+            BrowserColumn::Tracks => self.album().map_or(0, |album| album.tracks.len()),
+        }
+    }
+
+    /// Cursor row in the column the cursor is in
+    pub fn cursor(&self) -> usize {
+        self.selected[self.column as usize]
+    }
+
+    /// Put the cursor on a row of its column; the columns to the right start
+    /// over at their first row when it moves
+    pub fn select(&mut self, row: usize) {
+        let column = self.column as usize;
+        if self.selected[column] != row {
+            self.selected[column] = row;
+            self.selected[column + 1..].fill(0);
+        }
+    }
+
+    /// Select the previous row, wrapping around to the last one
+    pub fn select_previous(&mut self) {
+        let rows = self.column_len();
+        if rows > 0 {
+            self.select((self.cursor() + rows - 1) % rows);
+        }
+    }
+
+    /// Select the next row, wrapping around to the first one
+    pub fn select_next(&mut self) {
+        let rows = self.column_len();
+        if rows > 0 {
+            self.select((self.cursor() + 1) % rows);
+        }
+    }
+
+    /// Move the cursor into the column to the right
+    pub fn enter_column(&mut self) {
+        let next = match self.column {
+            BrowserColumn::Artists => BrowserColumn::Albums,
+            BrowserColumn::Albums | BrowserColumn::Tracks => BrowserColumn::Tracks,
+        };
+        let previous = std::mem::replace(&mut self.column, next);
+        if self.column_len() == 0 {
+            self.column = previous;
+        }
+    }
+
+    /// Move the cursor back into the column to the left
+    pub fn leave_column(&mut self) {
+        self.column = match self.column {
+            BrowserColumn::Artists | BrowserColumn::Albums => BrowserColumn::Artists,
+            BrowserColumn::Tracks => BrowserColumn::Albums,
+        };
+    }
+
+    /// Library index of the track under the cursor, None outside the track
+    /// column
+    pub fn selected_track(&self) -> Option<usize> {
+        if self.column != BrowserColumn::Tracks {
+            return None;
+        }
+        self.album()?.tracks.get(self.selected[2]).copied()
+    }
+
+    /// Library indices of the tracks under the cursor: every album of the
+    /// artist, the album, or the track
+    pub fn selected_tracks(&self) -> Vec<usize> {
+        match self.column {
+            BrowserColumn::Artists => self
+                .artist()
+                .map(|artist| {
+                    artist
+                        .albums
+                        .iter()
+                        .flat_map(|album| album.tracks.iter().copied())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            BrowserColumn::Albums => self
+                .album()
+                .map(|album| album.tracks.clone())
+                .unwrap_or_default(),
+            BrowserColumn::Tracks => self.selected_track().into_iter().collect(),
+        }
+    }
+
+    /// Library indices of the tracks Enter queues for the cursor: like
+    /// `selected_tracks`, except that a track takes the rest of its album along
+    pub fn tracks_to_queue(&self) -> Vec<usize> {
+        match (self.column, self.album()) {
+            (BrowserColumn::Tracks, Some(album)) => album
+                .tracks
+                .get(self.selected[2]..)
+                .map(<[usize]>::to_vec)
+                .unwrap_or_default(),
+            _ => self.selected_tracks(),
+        }
+    }
+
+    /// Put the cursor on a track in the track column. Returns the track's row.
+    pub fn reveal(&mut self, index: usize) -> Option<usize> {
+        let (artist, album, track) =
+            self.artists
+                .iter()
+                .enumerate()
+                .find_map(|(artist, group)| {
+                    group.albums.iter().enumerate().find_map(|(album, group)| {
+                        let track = group.tracks.iter().position(|&i| i == index)?;
+                        Some((artist, album, track))
+                    })
+                })?;
+        self.selected = [artist, album, track];
+        self.column = BrowserColumn::Tracks;
+        Some(track)
+    }
+}
+
+/// Group tracks by album artist and album
+fn group_tracks(tracks: &[Track]) -> Vec<ArtistGroup> {
+    let mut artists: Vec<ArtistGroup> = Vec::new();
+    let mut artist_rows: HashMap<String, usize> = HashMap::new();
+    for (index, track) in tracks.iter().enumerate() {
+        let name = track
+            .album_artist
+            .as_deref()
+            .or(track.artist.as_deref())
+            .unwrap_or("Unknown Artist");
+        let row = *artist_rows
+            .entry(group_key(name))
+            .or_insert_with_key(|key| {
+                artists.push(ArtistGroup {
+                    name: name.to_string(),
+                    albums: Vec::new(),
+                    key: key.clone(),
+                });
+                artists.len() - 1
+            });
+
+        let title = track.album.as_deref().unwrap_or("Unknown Album");
+        let key = group_key(title);
+        let albums = &mut artists[row].albums;
+        let album = match albums.iter_mut().find(|album| album.key == key) {
+            Some(album) => album,
+            None => {
+                albums.push(AlbumGroup {
+                    title: title.to_string(),
+                    year: None,
+                    tracks: Vec::new(),
+                    multi_disc: false,
+                    key,
+                    paths: Vec::new(),
+                });
+                albums.last_mut().expect("album was just added")
+            }
+        };
+        album.tracks.push(index);
+        album.year = match (album.year, track.year) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    artists.sort_by(|a, b| a.key.cmp(&b.key));
+    for artist in &mut artists {
+        // Undated albums go last
+        artist.albums.sort_by(|a, b| {
+            (a.year.is_none(), a.year, &a.key).cmp(&(b.year.is_none(), b.year, &b.key))
+        });
+        for album in &mut artist.albums {
+            album
+                .tracks
+                .sort_by(|&a, &b| track_order(&tracks[a], &tracks[b]));
+            album.paths = album
+                .tracks
+                .iter()
+                .map(|&i| tracks[i].path.clone())
+                .collect();
+            let mut discs = album.tracks.iter().map(|&i| tracks[i].disc_number);
+            let first = discs.next().flatten();
+            album.multi_disc = discs.any(|disc| disc != first);
+        }
+    }
+    artists
+}
+
+/// Tracks in disc and track number order, unnumbered ones last by file name
+fn track_order(a: &Track, b: &Track) -> Ordering {
+    fn key(track: &Track) -> (u32, bool, Option<u32>, Option<&OsStr>) {
+        (
+            track.disc_number.unwrap_or(1),
+            track.track_number.is_none(),
+            track.track_number,
+            track.path.file_name(),
+        )
+    }
+    key(a).cmp(&key(b))
+}
+
+/// Names that differ only in case or surrounding spaces group together
+fn group_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
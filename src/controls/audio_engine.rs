@@ -1,18 +1,53 @@
-use rodio::{OutputStream, OutputStreamHandle, Sink};
+use parking_lot::Mutex;
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::log_error;
 
+use super::equalizer::{Equalizer, EqualizerSettings, SharedEqualizer, EQ_BAND_COUNT};
+use super::fade::{Fade, FadeHandle};
+use super::replay_gain::{GainHandle, ReplayGainSource};
+use super::sample_tap::{SampleTap, TapBuffer};
 use super::sound_control::SoundControl;
+use super::time_stretch::{SharedTimeStretch, TimeStretch, TimeStretchSettings};
+use super::tracked_source::{TrackProgress, TrackedSource};
+
+/// Shared handles into the processing chain of one track
+struct TrackHandles {
+    progress: Arc<TrackProgress>,
+    fade: Arc<FadeHandle>,
+    gain: Arc<GainHandle>,
+}
+
+impl TrackHandles {
+    fn new(gain: f32) -> Self {
+        Self {
+            progress: Arc::new(TrackProgress::default()),
+            fade: Arc::new(FadeHandle::default()),
+            gain: Arc::new(GainHandle::new(gain)),
+        }
+    }
+}
 
 pub struct AudioEngine {
     sink: Sink,
+    /// Sinks of previous tracks while they fade out under the current one;
+    /// more than one when tracks are skipped faster than they fade
+    fading_sinks: Vec<Sink>,
+    equalizer: SharedEqualizer,
+    time_stretch: SharedTimeStretch,
+    current: TrackHandles,
+    /// The track appended behind the playing one, if any
+    queued: Option<TrackHandles>,
+    /// Samples on their way to the speakers, for the live spectrum
+    tap: Arc<TapBuffer>,
     _stream: OutputStream,
-    _stream_handle: OutputStreamHandle,
+    stream_handle: OutputStreamHandle,
 }
 
 impl AudioEngine {
@@ -22,31 +57,145 @@ impl AudioEngine {
 
         Ok(Self {
             sink,
+            fading_sinks: Vec::new(),
+            equalizer: Arc::new(Mutex::new(EqualizerSettings::default())),
+            time_stretch: Arc::new(Mutex::new(TimeStretchSettings::default())),
+            current: TrackHandles::new(1.0),
+            queued: None,
+            tap: Arc::new(TapBuffer::default()),
             _stream: stream,
-            _stream_handle: stream_handle,
+            stream_handle,
         })
     }
 
-    pub fn play(&mut self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    /// Play a track right away. `gain` is its ReplayGain factor.
+    pub fn play(&mut self, path: impl AsRef<Path>, gain: f32) -> Result<(), Box<dyn Error>> {
         self.sink.clear();
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let source = rodio::Decoder::new(reader)?;
+        self.fading_sinks.clear();
+        self.queued = None;
+        self.current = TrackHandles::new(gain);
+        let source = self.open(path, &self.current, Duration::ZERO)?;
+
         self.sink.append(source);
         self.sink.play();
         Ok(())
     }
 
+    /// Start a track on a fresh sink while the playing one fades out, both over
+    /// `duration`
+    pub fn crossfade(
+        &mut self,
+        path: impl AsRef<Path>,
+        duration: Duration,
+        gain: f32,
+    ) -> Result<(), Box<dyn Error>> {
+        let handles = TrackHandles::new(gain);
+        let source = self.open(path, &handles, duration)?;
+        let sink = Sink::try_new(&self.stream_handle)?;
+        sink.set_volume(self.sink.volume());
+        sink.append(source);
+
+        // Whatever is still audible on the outgoing sink fades out and ends
+        self.cancel_queued();
+        if let Some(queued) = self.queued.take() {
+            queued.fade.fade_out(duration);
+        }
+        self.current.fade.fade_out(duration);
+
+        // Earlier fades keep running to their end; finished ones are let go
+        self.fading_sinks.retain(|sink| !sink.empty());
+        self.fading_sinks
+            .push(std::mem::replace(&mut self.sink, sink));
+        self.current = handles;
+        Ok(())
+    }
+
+    /// Decode the next track ahead of time and append it behind the playing one, so
+    /// its samples follow the last sample of the current track without a gap
+    pub fn enqueue(&mut self, path: impl AsRef<Path>, gain: f32) -> Result<(), Box<dyn Error>> {
+        self.cancel_queued();
+        if self.queued.is_some() {
+            return Err("Playback already moved on to the queued track".into());
+        }
+        let handles = TrackHandles::new(gain);
+        let source = self.open(path, &handles, Duration::ZERO)?;
+
+        self.sink.append(source);
+        self.queued = Some(handles);
+        Ok(())
+    }
+
+    /// Drop the appended track, unless playback already crossed over to it
+    pub fn cancel_queued(&mut self) {
+        if let Some(queued) = self.queued.take() {
+            if !queued.progress.cancel() {
+                self.queued = Some(queued);
+            }
+        }
+    }
+
+    pub fn has_queued(&self) -> bool {
+        self.queued.is_some()
+    }
+
+    /// Returns true once, when the audio thread has moved on to the appended track.
+    /// From then on positions are reported in the new track's timeline.
+    pub fn switch_to_queued(&mut self) -> bool {
+        match self.queued.take() {
+            Some(queued) if queued.progress.has_started() => {
+                self.current = queued;
+                true
+            }
+            queued => {
+                self.queued = queued;
+                false
+            }
+        }
+    }
+
+    /// Build the processing chain for one track
+    fn open(
+        &self,
+        path: impl AsRef<Path>,
+        handles: &TrackHandles,
+        fade_in: Duration,
+    ) -> Result<impl Source<Item = f32> + Send + 'static, Box<dyn Error>> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        let source = rodio::Decoder::new(reader)?.convert_samples::<f32>();
+
+        // Normalize before the equalizer, which keeps the output within full scale
+        let source = ReplayGainSource::new(source, Arc::clone(&handles.gain));
+        let source = Equalizer::new(source, Arc::clone(&self.equalizer));
+        let source = TimeStretch::new(source, Arc::clone(&self.time_stretch));
+        // Fades come last so their length is in real time whatever the tempo
+        let source = Fade::new(source, Arc::clone(&handles.fade), fade_in);
+        let source = SampleTap::new(source, Arc::clone(&self.tap));
+        // Track position is taken last, from what actually goes out
+        Ok(TrackedSource::new(
+            source,
+            Arc::clone(&handles.progress),
+            Arc::clone(&self.time_stretch),
+        ))
+    }
+
     pub fn pause(&mut self) {
         self.sink.pause();
+        for sink in &self.fading_sinks {
+            sink.pause();
+        }
     }
 
     pub fn resume(&mut self) {
         self.sink.play();
+        for sink in &self.fading_sinks {
+            sink.play();
+        }
     }
 
     // TODO: Handler error better
     pub fn stop(&mut self) {
+        self.fading_sinks.clear();
         match self.sink.try_seek(Duration::ZERO) {
             Ok(_) => {
                 self.sink.pause();
@@ -74,33 +223,51 @@ impl AudioEngine {
         // Set volume (0.0 to 1.0 scale for rodio)
         let volume = sound_control.volume() / 100.0;
         self.sink.set_volume(volume);
+        for sink in &self.fading_sinks {
+            sink.set_volume(volume);
+        }
 
-        // // Set pitch (map -100..100 to -1.0..1.0 range)
-        // let pitch = 1.0 + (sound_control.pitch() / 100.0);
-        // self.sink.set_speed(pitch);
+        // Bass and treble are picked up by the Equalizer source of the playing track
+        {
+            let mut equalizer = self.equalizer.lock();
+            equalizer.bass_db = Self::calc_shelf_gain(sound_control.bass());
+            equalizer.treble_db = Self::calc_shelf_gain(sound_control.treble());
+        }
 
-        let speed = Self::calc_playback_speed(sound_control.pitch());
-        self.sink.set_speed(speed);
+        // Pitch and tempo are picked up by the TimeStretch source
+        let mut time_stretch = self.time_stretch.lock();
+        time_stretch.pitch_semitones = sound_control.pitch();
+        time_stretch.tempo = sound_control.tempo() / 100.0;
+    }
 
-        // For bass, treble and pitch, we would ideally use the AudioProcessor,
-        // but since rodio doesn't provide built-in equalization, we'll rely on
-        // just setting the volume for now.
-        // The AudioProcessor would need to be more complex to implement true
-        // equalization and pitch control.
+    /// Update the ReplayGain factors of the playing and the queued track
+    pub fn set_replay_gain(&mut self, current: f32, queued: Option<f32>) {
+        self.current.gain.set(current);
+        if let (Some(handles), Some(gain)) = (&self.queued, queued) {
+            handles.gain.set(gain);
+        }
+    }
+
+    pub fn apply_graphic_eq(&mut self, gains: &[f32; EQ_BAND_COUNT]) {
+        self.equalizer.lock().bands = *gains;
     }
 
+    /// Position in the playing track's own timeline, independent of the tempo
     pub fn get_current_pos(&self) -> std::time::Duration {
-        self.sink.get_pos()
+        self.current.progress.position()
+    }
+
+    pub fn tap(&self) -> Arc<TapBuffer> {
+        Arc::clone(&self.tap)
     }
 
     pub fn is_sink_empty(&self) -> bool {
         self.sink.empty()
     }
 
-    pub fn calc_playback_speed(pitch: f32) -> f32 {
-        let pitch_factor = pitch / 100.0;
-        let semitone_range = 24.0;
-        let speed = 2.0f32.powf(pitch_factor * semitone_range / 12.0);
-        speed.clamp(0.5, 2.0)
+    /// Map a 0..100 tone control to a shelf gain in dB, 50 being flat
+    pub fn calc_shelf_gain(level: f32) -> f32 {
+        let max_gain_db = 12.0;
+        ((level - 50.0) / 50.0 * max_gain_db).clamp(-max_gain_db, max_gain_db)
     }
 }
@@ -0,0 +1,277 @@
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use crate::controls::spectrum::SpectrumFrame;
+
+/// Autosens lowers the gain this fast while bands overshoot the ceiling
+const AUTOSENS_FALL_DB_PER_SECOND: f32 = 30.0;
+/// and raises it this slowly while the music stays below it
+const AUTOSENS_RISE_DB_PER_SECOND: f32 = 2.0;
+/// Range the automatic gain stays within
+const AUTOSENS_LIMIT_DB: f32 = 30.0;
+/// Below this level the input counts as silence and the gain is left alone
+const AUTOSENS_SILENCE: f32 = 0.05;
+/// How fast peak markers fall back, in bar heights per second
+const PEAK_FALL_PER_SECOND: f32 = 0.6;
+/// Longest time step the smoothing takes, so a stalled UI does not jump
+const MAX_STEP_SECONDS: f32 = 0.1;
+
+/// How band edges are spread over the frequency range
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BandScale {
+    /// Every band spans the same fraction of an octave
+    #[default]
+    Log,
+    /// Bands follow the mel scale, giving the mids more room than `Log`
+    Mel,
+}
+
+impl BandScale {
+    /// Position of a frequency on this scale
+    fn position(self, frequency: f32) -> f32 {
+        match self {
+            BandScale::Log => frequency.ln(),
+            BandScale::Mel => 2595.0 * (1.0 + frequency / 700.0).log10(),
+        }
+    }
+
+    /// Frequency at a position on this scale
+    fn frequency(self, value: f32) -> f32 {
+        match self {
+            BandScale::Log => value.exp(),
+            BandScale::Mel => 700.0 * (10f32.powf(value / 2595.0) - 1.0),
+        }
+    }
+}
+
+/// How FFT magnitudes become visualizer bands, read from the `bands` section of
+/// the config file
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct BandSettings {
+    pub scale: BandScale,
+    /// Lowest frequency shown, in Hz
+    pub min_frequency: f32,
+    /// Highest frequency shown, in Hz, limited to half the sample rate
+    pub max_frequency: f32,
+    /// Level of an empty bar
+    pub floor_db: f32,
+    /// Level of a full bar
+    pub ceiling_db: f32,
+    /// Adjust the gain so the loudest bands just reach the top, like cava's autosens
+    pub autosens: bool,
+    /// Time constant of rising bars, in milliseconds
+    pub attack_ms: f32,
+    /// Time constant of falling bars, in milliseconds
+    pub decay_ms: f32,
+}
+
+impl Default for BandSettings {
+    fn default() -> Self {
+        Self {
+            scale: BandScale::Log,
+            min_frequency: 40.0,
+            max_frequency: 16000.0,
+            floor_db: -70.0,
+            ceiling_db: -10.0,
+            autosens: true,
+            attack_ms: 25.0,
+            decay_ms: 250.0,
+        }
+    }
+}
+
+/// Bar heights from 0 to 1, lowest frequency first, with a slowly falling peak
+/// per bar
+#[derive(Debug, Default)]
+pub struct Bands {
+    pub values: Vec<f32>,
+    pub peaks: Vec<f32>,
+}
+
+/// FFT bins contributing to each band, with the fraction of each bin that falls
+/// inside the band
+struct BandLayout {
+    bins: usize,
+    sample_rate: u32,
+    weights: Vec<Vec<(usize, f32)>>,
+}
+
+impl BandLayout {
+    fn new(settings: &BandSettings, bins: usize, sample_rate: u32, count: usize) -> Self {
+        let bin_hz = sample_rate as f32 / bins as f32;
+        let nyquist = sample_rate as f32 / 2.0;
+        let max_frequency = settings.max_frequency.min(nyquist);
+        let min_frequency = settings.min_frequency.clamp(1.0, max_frequency / 2.0);
+
+        let low = settings.scale.position(min_frequency);
+        let high = settings.scale.position(max_frequency);
+        let edge = |band: usize| {
+            let value = low + (high - low) * band as f32 / count as f32;
+            settings.scale.frequency(value) / bin_hz
+        };
+
+        let weights = (0..count)
+            .map(|band| {
+                // Bin k covers k - 0.5 to k + 0.5 in units of bins
+                let (start, end) = (edge(band), edge(band + 1));
+                let first = (start + 0.5).floor() as usize;
+                let last = ((end + 0.5).ceil() as usize).min(bins / 2 + 1);
+                (first..last)
+                    .filter_map(|bin| {
+                        let center = bin as f32;
+                        let overlap = end.min(center + 0.5) - start.max(center - 0.5);
+                        (overlap > 0.0).then_some((bin, overlap))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            bins,
+            sample_rate,
+            weights,
+        }
+    }
+}
+
+/// Turns spectrum frames into a fixed number of well-scaled bands that every
+/// visualizer canvas draws from.
+///
+/// The energy of the FFT bins is summed into log or mel spaced bands, converted
+/// to decibels and mapped from the floor to the ceiling. An automatic gain keeps
+/// quiet and loud tracks using the full height, and the result is smoothed with
+/// separate attack and decay times.
+pub struct BandMapper {
+    settings: BandSettings,
+    layout: Option<BandLayout>,
+    /// Automatic gain in dB, zero when autosens is off
+    gain_db: f32,
+    bands: Bands,
+    last_update: Option<Instant>,
+}
+
+impl BandMapper {
+    pub fn new(settings: BandSettings) -> Self {
+        Self {
+            settings,
+            layout: None,
+            gain_db: 0.0,
+            bands: Bands::default(),
+            last_update: None,
+        }
+    }
+
+    /// Map the latest spectrum frame onto `count` bands. An empty frame lets the
+    /// bars fall back to zero.
+    pub fn update(&mut self, frame: &SpectrumFrame, count: usize) {
+        let now = Instant::now();
+        let step = self
+            .last_update
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32())
+            .min(MAX_STEP_SECONDS);
+        self.last_update = Some(now);
+
+        if self.bands.values.len() != count {
+            self.bands.values = vec![0.0; count];
+            self.bands.peaks = vec![0.0; count];
+        }
+
+        let targets = self.levels(frame, count);
+        if self.settings.autosens {
+            self.adjust_gain(&targets, step);
+        }
+
+        let attack = smoothing(step, self.settings.attack_ms);
+        let decay = smoothing(step, self.settings.decay_ms);
+        let gain = self.gain_db / self.range_db();
+        for ((value, peak), target) in self
+            .bands
+            .values
+            .iter_mut()
+            .zip(self.bands.peaks.iter_mut())
+            .zip(&targets)
+        {
+            let target = (target + gain).clamp(0.0, 1.0);
+            let rate = if target > *value { attack } else { decay };
+            *value += (target - *value) * rate;
+
+            *peak = (*peak - PEAK_FALL_PER_SECOND * step).max(*value);
+        }
+    }
+
+    /// Bands computed by the last `update`
+    pub fn bands(&self) -> &Bands {
+        &self.bands
+    }
+
+    /// Level of every band between floor (0) and ceiling (1), before the
+    /// automatic gain and unclamped. Without a spectrum every band is silent,
+    /// whatever the gain.
+    fn levels(&mut self, frame: &SpectrumFrame, count: usize) -> Vec<f32> {
+        let bins = frame.magnitudes.len();
+        if bins < 2 || frame.sample_rate == 0 || count == 0 {
+            return vec![f32::NEG_INFINITY; count];
+        }
+
+        let stale = self.layout.as_ref().is_none_or(|layout| {
+            layout.bins != bins
+                || layout.sample_rate != frame.sample_rate
+                || layout.weights.len() != count
+        });
+        if stale {
+            self.layout = Some(BandLayout::new(
+                &self.settings,
+                bins,
+                frame.sample_rate,
+                count,
+            ));
+        }
+        let Some(layout) = &self.layout else {
+            return vec![f32::NEG_INFINITY; count];
+        };
+
+        // A full-scale sine has a magnitude of about half the FFT size
+        let reference = bins as f32 / 2.0;
+        layout
+            .weights
+            .iter()
+            .map(|weights| {
+                let energy: f32 = weights
+                    .iter()
+                    .map(|&(bin, weight)| weight * frame.magnitudes[bin].powi(2))
+                    .sum();
+                let db = 10.0 * (energy / (reference * reference)).max(1e-12).log10();
+                (db - self.settings.floor_db) / self.range_db()
+            })
+            .collect()
+    }
+
+    fn adjust_gain(&mut self, levels: &[f32], step: f32) {
+        let loudest = levels
+            .iter()
+            .map(|level| level + self.gain_db / self.range_db())
+            .fold(0.0, f32::max);
+        if loudest > 1.0 {
+            self.gain_db -= AUTOSENS_FALL_DB_PER_SECOND * step;
+        } else if loudest > AUTOSENS_SILENCE {
+            self.gain_db += AUTOSENS_RISE_DB_PER_SECOND * step;
+        }
+        self.gain_db = self.gain_db.clamp(-AUTOSENS_LIMIT_DB, AUTOSENS_LIMIT_DB);
+    }
+
+    fn range_db(&self) -> f32 {
+        (self.settings.ceiling_db - self.settings.floor_db).max(1.0)
+    }
+}
+
+/// Fraction of the way to the target covered in `step` seconds for a time
+/// constant in milliseconds
+fn smoothing(step: f32, time_constant_ms: f32) -> f32 {
+    if time_constant_ms <= 0.0 {
+        return 1.0;
+    }
+    1.0 - (-step * 1000.0 / time_constant_ms).exp()
+}
@@ -0,0 +1,334 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Corner frequency of the bass low-shelf filter in Hz
+const BASS_FREQUENCY: f32 = 200.0;
+/// Corner frequency of the treble high-shelf filter in Hz
+const TREBLE_FREQUENCY: f32 = 3500.0;
+/// Butterworth Q, gives a shelf without overshoot
+const SHELF_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+/// Q of the graphic bands, roughly one octave wide so neighbours blend smoothly
+const BAND_Q: f32 = 1.41;
+/// Number of frames processed between two parameter refreshes
+const UPDATE_INTERVAL: usize = 64;
+/// Largest gain change (in dB) applied per refresh, so a key press glides
+/// to its target over a few milliseconds instead of clicking
+const MAX_GAIN_STEP_DB: f32 = 0.2;
+/// Gains below this are treated as flat and the filter is bypassed
+const FLAT_THRESHOLD_DB: f32 = 0.01;
+
+/// Number of bands of the graphic equalizer
+pub const EQ_BAND_COUNT: usize = 10;
+/// Center frequencies of the graphic equalizer bands in Hz (ISO octave bands)
+pub const EQ_FREQUENCIES: [f32; EQ_BAND_COUNT] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+/// Bass shelf, treble shelf and one peaking filter per graphic band
+const FILTER_COUNT: usize = EQ_BAND_COUNT + 2;
+
+/// Target gains for the equalizer stage, shared between the UI thread and the
+/// audio thread.
+///
+/// The UI side writes new targets through `AudioEngine::apply_effects` and
+/// `AudioEngine::apply_graphic_eq`, while the `Equalizer` source polls them every
+/// few milliseconds and glides towards them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EqualizerSettings {
+    pub bass_db: f32,
+    pub treble_db: f32,
+    pub bands: [f32; EQ_BAND_COUNT],
+}
+
+impl EqualizerSettings {
+    /// All filter gains in processing order: bass, treble, then the graphic bands
+    fn gains(&self) -> [f32; FILTER_COUNT] {
+        let mut gains = [0.0; FILTER_COUNT];
+        gains[0] = self.bass_db;
+        gains[1] = self.treble_db;
+        gains[2..].copy_from_slice(&self.bands);
+        gains
+    }
+}
+
+pub type SharedEqualizer = Arc<Mutex<EqualizerSettings>>;
+
+#[derive(Debug, Clone, Copy)]
+enum FilterKind {
+    LowShelf,
+    HighShelf,
+    Peaking,
+}
+
+/// Filter kind and frequency for every slot of `EqualizerSettings::gains`
+fn filter_layout(index: usize) -> (FilterKind, f32) {
+    match index {
+        0 => (FilterKind::LowShelf, BASS_FREQUENCY),
+        1 => (FilterKind::HighShelf, TREBLE_FREQUENCY),
+        _ => (FilterKind::Peaking, EQ_FREQUENCIES[index - 2]),
+    }
+}
+
+/// Second order IIR filter coefficients (RBJ audio EQ cookbook), normalized by `a0`.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    /// Pass-through filter
+    const IDENTITY: Self = Self {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+
+    fn new(kind: FilterKind, sample_rate: u32, frequency: f32, gain_db: f32) -> Self {
+        // Bands that would sit on or above Nyquist cannot be represented
+        if frequency >= sample_rate as f32 * 0.45 {
+            return match kind {
+                FilterKind::LowShelf | FilterKind::HighShelf => {
+                    Self::shelf(kind, sample_rate, sample_rate as f32 * 0.45, gain_db)
+                }
+                FilterKind::Peaking => Self::IDENTITY,
+            };
+        }
+
+        match kind {
+            FilterKind::LowShelf | FilterKind::HighShelf => {
+                Self::shelf(kind, sample_rate, frequency, gain_db)
+            }
+            FilterKind::Peaking => Self::peaking(sample_rate, frequency, gain_db),
+        }
+    }
+
+    fn shelf(kind: FilterKind, sample_rate: u32, frequency: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let (sin_w0, cos_w0) = w0.sin_cos();
+        let alpha = sin_w0 / (2.0 * SHELF_Q);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = if matches!(kind, FilterKind::HighShelf) {
+            (
+                a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0),
+                a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos_w0),
+                (a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha,
+            )
+        } else {
+            (
+                a * ((a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0),
+                a * ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                (a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0),
+                (a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha,
+            )
+        };
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    fn peaking(sample_rate: u32, frequency: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let (sin_w0, cos_w0) = w0.sin_cos();
+        let alpha = sin_w0 / (2.0 * BAND_Q);
+
+        Self::normalized(
+            1.0 + alpha * a,
+            -2.0 * cos_w0,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos_w0,
+            1.0 - alpha / a,
+        )
+    }
+
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    /// Transposed direct form II, which tolerates coefficient changes between
+    /// samples without audible artifacts
+    #[inline]
+    fn process(&self, state: &mut [f32; 2], input: f32) -> f32 {
+        let output = self.b0 * input + state[0];
+        state[0] = self.b1 * input - self.a1 * output + state[1];
+        state[1] = self.b2 * input - self.a2 * output;
+        output
+    }
+}
+
+/// A `Source` wrapper applying bass (low-shelf), treble (high-shelf) and the
+/// ten graphic equalizer bands (peaking) to the wrapped stream.
+///
+/// Coefficients follow the shared `EqualizerSettings` while the track is playing,
+/// so changing the sound controls never restarts or re-opens the track.
+pub struct Equalizer<S> {
+    input: S,
+    settings: SharedEqualizer,
+    current: [f32; FILTER_COUNT],
+    target: [f32; FILTER_COUNT],
+    filters: [Biquad; FILTER_COUNT],
+    // Per channel filter memory, one [z1, z2] pair per filter
+    state: Vec<[[f32; 2]; FILTER_COUNT]>,
+    sample_rate: u32,
+    channels: u16,
+    channel: usize,
+    frames_until_update: usize,
+}
+
+impl<S> Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, settings: SharedEqualizer) -> Self {
+        let target = settings.lock().gains();
+        let sample_rate = input.sample_rate();
+        let channels = input.channels();
+
+        let mut filters = [Biquad::IDENTITY; FILTER_COUNT];
+        for (index, filter) in filters.iter_mut().enumerate() {
+            let (kind, frequency) = filter_layout(index);
+            *filter = Biquad::new(kind, sample_rate, frequency, target[index]);
+        }
+
+        Self {
+            input,
+            settings,
+            current: target,
+            target,
+            filters,
+            state: vec![[[0.0; 2]; FILTER_COUNT]; channels as usize],
+            sample_rate,
+            channels,
+            channel: 0,
+            frames_until_update: UPDATE_INTERVAL,
+        }
+    }
+
+    /// Pull new targets from the shared settings and glide towards them
+    fn refresh(&mut self) {
+        // Never block the audio thread; the UI will be done on the next refresh
+        if let Some(settings) = self.settings.try_lock() {
+            self.target = settings.gains();
+        }
+
+        let sample_rate = self.input.sample_rate();
+        let channels = self.input.channels();
+        let format_changed = sample_rate != self.sample_rate || channels != self.channels;
+        if format_changed {
+            self.sample_rate = sample_rate;
+            self.channels = channels;
+            self.state = vec![[[0.0; 2]; FILTER_COUNT]; channels as usize];
+        }
+
+        for index in 0..FILTER_COUNT {
+            let gain_db = step_towards(self.current[index], self.target[index]);
+            if format_changed || gain_db != self.current[index] {
+                let (kind, frequency) = filter_layout(index);
+                self.current[index] = gain_db;
+                self.filters[index] = Biquad::new(kind, self.sample_rate, frequency, gain_db);
+            }
+        }
+    }
+}
+
+fn step_towards(current: f32, target: f32) -> f32 {
+    current + (target - current).clamp(-MAX_GAIN_STEP_DB, MAX_GAIN_STEP_DB)
+}
+
+impl<S> Iterator for Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            self.frames_until_update -= 1;
+            if self.frames_until_update == 0 {
+                self.frames_until_update = UPDATE_INTERVAL;
+                self.refresh();
+            }
+        }
+
+        let sample = self.input.next()?;
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % self.channels.max(1) as usize;
+
+        let state = &mut self.state[channel];
+        let mut output = sample;
+        for (index, filter) in self.filters.iter().enumerate() {
+            if self.current[index].abs() >= FLAT_THRESHOLD_DB {
+                output = filter.process(&mut state[index], output);
+            } else {
+                // Start from silence rather than stale memory once re-enabled
+                state[index] = [0.0; 2];
+            }
+        }
+
+        Some(output.clamp(-1.0, 1.0))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        // Old filter memory belongs to a different part of the track
+        self.state
+            .iter_mut()
+            .for_each(|s| *s = [[0.0; 2]; FILTER_COUNT]);
+        self.channel = 0;
+        Ok(())
+    }
+}
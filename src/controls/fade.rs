@@ -0,0 +1,151 @@
+use std::f32::consts::FRAC_PI_2;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Lets the UI thread ask a playing track to fade out.
+#[derive(Debug, Default)]
+pub struct FadeHandle {
+    /// Requested fade-out length in microseconds, 0 while none was requested
+    fade_out_micros: AtomicU64,
+}
+
+impl FadeHandle {
+    /// Fade the track out over `duration` starting now, after which it ends
+    pub fn fade_out(&self, duration: Duration) {
+        let micros = (duration.as_micros() as u64).max(1);
+        self.fade_out_micros.store(micros, Ordering::Relaxed);
+    }
+
+    fn requested_fade_out(&self) -> Option<Duration> {
+        match self.fade_out_micros.load(Ordering::Relaxed) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+}
+
+/// A `Source` wrapper applying equal-power fades for crossfading.
+///
+/// The fade-in follows a sine curve and the fade-out a cosine curve, so the summed
+/// power of two overlapping tracks stays constant throughout the crossfade.
+pub struct Fade<S> {
+    input: S,
+    handle: Arc<FadeHandle>,
+    channels: usize,
+    /// Frames produced so far
+    frame: usize,
+    /// Sample index within the current frame
+    channel: usize,
+    fade_in_frames: usize,
+    /// Start frame and length of a running fade-out
+    fade_out: Option<(usize, usize)>,
+    gain: f32,
+}
+
+impl<S> Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, handle: Arc<FadeHandle>, fade_in: Duration) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let fade_in_frames = Self::frames(&input, fade_in);
+        Self {
+            input,
+            handle,
+            channels,
+            frame: 0,
+            channel: 0,
+            fade_in_frames,
+            fade_out: None,
+            gain: if fade_in_frames > 0 { 0.0 } else { 1.0 },
+        }
+    }
+
+    fn frames(input: &S, duration: Duration) -> usize {
+        (duration.as_secs_f64() * input.sample_rate() as f64) as usize
+    }
+
+    /// Gain for the frame at `self.frame`, None once a fade-out has completed
+    fn frame_gain(&mut self) -> Option<f32> {
+        if self.fade_out.is_none() {
+            if let Some(duration) = self.handle.requested_fade_out() {
+                let frames = Self::frames(&self.input, duration).max(1);
+                self.fade_out = Some((self.frame, frames));
+            }
+        }
+
+        let mut gain = 1.0;
+        if self.frame < self.fade_in_frames {
+            gain *= (self.frame as f32 / self.fade_in_frames as f32 * FRAC_PI_2).sin();
+        }
+        if let Some((start, frames)) = self.fade_out {
+            let progress = (self.frame - start) as f32 / frames as f32;
+            if progress >= 1.0 {
+                return None;
+            }
+            gain *= (progress * FRAC_PI_2).cos();
+        }
+        Some(gain)
+    }
+}
+
+impl<S> Iterator for Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        // All channels of a frame share one gain
+        if self.channel == 0 {
+            self.gain = self.frame_gain()?;
+        }
+        let sample = self.input.next()?;
+
+        self.channel += 1;
+        if self.channel == self.channels {
+            self.channel = 0;
+            self.frame += 1;
+        }
+        Some(sample * self.gain)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
@@ -0,0 +1,309 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use crate::controls::music_library::{natural_cmp, MusicLibrary};
+
+/// A visible row of the folder tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeRow {
+    Folder {
+        path: PathBuf,
+        depth: usize,
+        expanded: bool,
+        /// Tracks anywhere below the folder
+        tracks: usize,
+    },
+    Track {
+        /// Index of the track in the library
+        index: usize,
+        path: PathBuf,
+        depth: usize,
+    },
+}
+
+impl TreeRow {
+    pub fn depth(&self) -> usize {
+        match self {
+            TreeRow::Folder { depth, .. } | TreeRow::Track { depth, .. } => *depth,
+        }
+    }
+}
+
+/// A file or folder name ordered the way the library orders its text:
+/// "Disc 2" before "Disc 10", regardless of case
+#[derive(Debug, PartialEq, Eq)]
+struct FolderName(OsString);
+
+impl Ord for FolderName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_names(&self.0, &other.0)
+    }
+}
+
+impl PartialOrd for FolderName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn compare_names(a: &OsStr, b: &OsStr) -> Ordering {
+    natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()).then_with(|| a.cmp(b))
+}
+
+/// A folder with the folders and tracks directly inside it
+#[derive(Debug, Default)]
+struct FolderNode {
+    path: PathBuf,
+    folders: BTreeMap<FolderName, FolderNode>,
+    /// File names, paths and library indices of the tracks
+    tracks: Vec<(OsString, PathBuf, usize)>,
+    /// Tracks anywhere below the folder
+    track_count: usize,
+}
+
+impl FolderNode {
+    /// Library indices of every track below the folder, in folder order:
+    /// subfolders first, then the folder's own tracks
+    fn collect_tracks(&self, indices: &mut Vec<usize>) {
+        for folder in self.folders.values() {
+            folder.collect_tracks(indices);
+        }
+        indices.extend(self.tracks.iter().map(|(_, _, index)| *index));
+    }
+}
+
+/// The library root as a collapsible tree of folders and tracks, in the layout
+/// the files have on disk. With several roots each is a top-level folder. The
+/// tree is rebuilt from the library when it changes; which folders are
+/// expanded is kept across rebuilds.
+#[derive(Debug, Default)]
+pub struct FolderTree {
+    root: FolderNode,
+    expanded: HashSet<PathBuf>,
+    /// The rows shown: the folders and tracks in expanded folders
+    pub rows: Vec<TreeRow>,
+    /// Cursor row
+    pub selected: usize,
+    /// Library revision the tree was built from
+    revision: u64,
+}
+
+impl FolderTree {
+    /// Rebuild the tree when the library has changed, keeping the cursor on
+    /// the same folder or track where it still exists
+    pub fn refresh(&mut self, library: &MusicLibrary) {
+        if self.revision == library.revision {
+            return;
+        }
+        let selected = self.selected_path();
+        self.revision = library.revision;
+
+        let roots = &library.roots;
+        let single_root = match roots.dirs.as_slice() {
+            [dir] => Some(dir),
+            _ => None,
+        };
+        self.root = FolderNode {
+            path: single_root.cloned().unwrap_or_default(),
+            ..FolderNode::default()
+        };
+        for (index, track) in library.tracks.iter().enumerate() {
+            let mut node = &mut self.root;
+            node.track_count += 1;
+            let mut relative = track.path.as_path();
+            if let Some(root) = roots.root_of(&track.path) {
+                let dir = &roots.dirs[root];
+                relative = track.path.strip_prefix(dir).unwrap_or(relative);
+                if single_root.is_none() {
+                    node = node
+                        .folders
+                        .entry(FolderName(dir.as_os_str().to_os_string()))
+                        .or_insert_with(|| FolderNode {
+                            path: dir.clone(),
+                            ..FolderNode::default()
+                        });
+                    node.track_count += 1;
+                }
+            }
+            if let Some(parent) = relative.parent() {
+                for component in parent.iter() {
+                    let path = node.path.join(component);
+                    node = node
+                        .folders
+                        .entry(FolderName(component.to_os_string()))
+                        .or_insert_with(|| FolderNode {
+                            path,
+                            ..FolderNode::default()
+                        });
+                    node.track_count += 1;
+                }
+            }
+            let name = relative.file_name().unwrap_or_default().to_os_string();
+            node.tracks.push((name, track.path.clone(), index));
+        }
+        sort_tracks(&mut self.root);
+
+        self.flatten();
+        if let Some(path) = selected {
+            self.select_path(&path);
+        }
+    }
+
+    /// Expand or collapse the folder under the cursor
+    pub fn toggle_selected(&mut self) {
+        if let Some(TreeRow::Folder { path, expanded, .. }) = self.rows.get(self.selected) {
+            if *expanded {
+                self.expanded.remove(path);
+            } else {
+                self.expanded.insert(path.clone());
+            }
+            self.flatten();
+        }
+    }
+
+    /// Collapse the expanded folder under the cursor, or else the folder the
+    /// cursor is in, moving the cursor onto it
+    pub fn collapse_selected(&mut self) {
+        let Some(row) = self.rows.get(self.selected) else {
+            return;
+        };
+        if !matches!(row, TreeRow::Folder { expanded: true, .. }) {
+            let depth = row.depth();
+            let Some(parent) = self.rows[..self.selected]
+                .iter()
+                .rposition(|row| row.depth() + 1 == depth)
+            else {
+                return;
+            };
+            self.selected = parent;
+        }
+        self.toggle_selected();
+    }
+
+    /// Select the previous row, wrapping around to the last one
+    pub fn select_previous(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        } else if !self.rows.is_empty() {
+            self.selected = self.rows.len() - 1;
+        }
+    }
+
+    /// Select the next row, wrapping around to the first one
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.rows.len() {
+            self.selected += 1;
+        } else {
+            self.selected = 0;
+        }
+    }
+
+    /// Library index of the track under the cursor, None on a folder
+    pub fn selected_track(&self) -> Option<usize> {
+        match self.rows.get(self.selected)? {
+            TreeRow::Track { index, .. } => Some(*index),
+            TreeRow::Folder { .. } => None,
+        }
+    }
+
+    /// Library indices of the tracks under the cursor: the track, or everything
+    /// below the folder in folder order
+    pub fn selected_tracks(&self) -> Vec<usize> {
+        match self.rows.get(self.selected) {
+            Some(TreeRow::Track { index, .. }) => vec![*index],
+            Some(TreeRow::Folder { path, .. }) => {
+                let mut indices = Vec::new();
+                if let Some(node) = self.node(path) {
+                    node.collect_tracks(&mut indices);
+                }
+                indices
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Expand the folders leading to a track and put the cursor on it.
+    /// Returns the track's row.
+    pub fn reveal(&mut self, index: usize, library: &MusicLibrary) -> Option<usize> {
+        let path = &library.tracks.get(index)?.path;
+        let mut folder = path.parent();
+        while let Some(path) = folder.filter(|folder| *folder != self.root.path) {
+            self.expanded.insert(path.to_path_buf());
+            folder = path.parent();
+        }
+        let path = path.clone();
+        self.flatten();
+        self.select_path(&path)
+    }
+
+    /// Path of the folder or track under the cursor
+    fn selected_path(&self) -> Option<PathBuf> {
+        match self.rows.get(self.selected)? {
+            TreeRow::Folder { path, .. } | TreeRow::Track { path, .. } => Some(path.clone()),
+        }
+    }
+
+    /// Put the cursor on the row of a folder or track, if it is shown
+    fn select_path(&mut self, path: &Path) -> Option<usize> {
+        let row = self.rows.iter().position(|row| match row {
+            TreeRow::Folder { path: row_path, .. } | TreeRow::Track { path: row_path, .. } => {
+                row_path == path
+            }
+        })?;
+        self.selected = row;
+        Some(row)
+    }
+
+    fn node(&self, path: &Path) -> Option<&FolderNode> {
+        let mut node = &self.root;
+        while node.path != path {
+            node = node
+                .folders
+                .values()
+                .find(|folder| path.starts_with(&folder.path))?;
+        }
+        Some(node)
+    }
+
+    /// List the rows of the folders and of the tracks in expanded folders
+    fn flatten(&mut self) {
+        let mut rows = Vec::new();
+        flatten_node(&self.root, 0, &self.expanded, &mut rows);
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+}
+
+fn sort_tracks(node: &mut FolderNode) {
+    node.tracks.sort_by(|(a, ..), (b, ..)| compare_names(a, b));
+    for folder in node.folders.values_mut() {
+        sort_tracks(folder);
+    }
+}
+
+fn flatten_node(
+    node: &FolderNode,
+    depth: usize,
+    expanded: &HashSet<PathBuf>,
+    rows: &mut Vec<TreeRow>,
+) {
+    for folder in node.folders.values() {
+        let is_expanded = expanded.contains(&folder.path);
+        rows.push(TreeRow::Folder {
+            path: folder.path.clone(),
+            depth,
+            expanded: is_expanded,
+            tracks: folder.track_count,
+        });
+        if is_expanded {
+            flatten_node(folder, depth + 1, expanded, rows);
+        }
+    }
+    rows.extend(node.tracks.iter().map(|(_, path, index)| TreeRow::Track {
+        index: *index,
+        path: path.clone(),
+        depth,
+    }));
+}
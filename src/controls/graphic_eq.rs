@@ -0,0 +1,237 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_config_dir;
+use crate::controls::equalizer::{EQ_BAND_COUNT, EQ_FREQUENCIES};
+use crate::{log_debug, log_error};
+
+/// Largest boost or cut of a single band in dB
+pub const MAX_BAND_GAIN_DB: f32 = 12.0;
+
+/// A named set of gains for the graphic equalizer bands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EqPreset {
+    pub name: String,
+    pub gains: [f32; EQ_BAND_COUNT],
+}
+
+/// What gets written to `equalizer.json` in the config directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EqState {
+    selected: Option<String>,
+    gains: Option<[f32; EQ_BAND_COUNT]>,
+    #[serde(default)]
+    user_presets: Vec<EqPreset>,
+}
+
+/// State of the 10-band graphic equalizer.
+///
+/// This struct is responsible for:
+/// - Holding the current gain of every band
+/// - Providing the built-in presets and the user's own presets
+/// - Tracking which preset and which band are selected
+/// - Persisting the selection and user presets to the config directory
+#[derive(Debug, Clone)]
+pub struct GraphicEq {
+    pub presets: Vec<EqPreset>,
+    pub selected_preset: usize,
+    pub selected_band: usize,
+    pub gains: [f32; EQ_BAND_COUNT],
+    /// Bands were changed by hand after the preset was selected
+    pub modified: bool,
+    /// Name being typed for a new user preset, None while not saving one
+    pub naming: Option<String>,
+    builtin_count: usize,
+}
+
+impl Default for GraphicEq {
+    fn default() -> Self {
+        let presets = builtin_presets();
+        Self {
+            builtin_count: presets.len(),
+            gains: presets[0].gains,
+            presets,
+            selected_preset: 0,
+            selected_band: 0,
+            modified: false,
+            naming: None,
+        }
+    }
+}
+
+fn builtin_presets() -> Vec<EqPreset> {
+    let preset = |name: &str, gains: [f32; EQ_BAND_COUNT]| EqPreset {
+        name: name.to_string(),
+        gains,
+    };
+
+    vec![
+        preset("Flat", [0.0; EQ_BAND_COUNT]),
+        preset("Rock", [5.0, 4.0, 3.0, 1.0, -1.0, -1.0, 1.0, 3.0, 4.0, 5.0]),
+        preset("Pop", [-1.0, 1.0, 3.0, 4.0, 3.0, 0.0, -1.0, -1.0, 1.0, 2.0]),
+        preset("Jazz", [3.0, 2.0, 1.0, 2.0, -1.0, -1.0, 0.0, 1.0, 2.0, 3.0]),
+        preset(
+            "Classical",
+            [4.0, 3.0, 2.0, 1.0, -1.0, -1.0, 0.0, 2.0, 3.0, 4.0],
+        ),
+        preset(
+            "Vocal",
+            [-3.0, -2.0, -1.0, 1.0, 3.0, 4.0, 4.0, 2.0, 0.0, -1.0],
+        ),
+        preset(
+            "Bass Boost",
+            [7.0, 6.0, 5.0, 3.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        ),
+        preset(
+            "Treble Boost",
+            [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 3.0, 5.0, 6.0, 7.0],
+        ),
+    ]
+}
+
+impl GraphicEq {
+    /// Load the equalizer state saved by a previous session, falling back to "Flat"
+    pub fn load() -> Self {
+        let mut graphic_eq = Self::default();
+
+        let state = match Self::state_path().map(fs::read_to_string) {
+            Some(Ok(content)) => match serde_json::from_str::<EqState>(&content) {
+                Ok(state) => state,
+                Err(e) => {
+                    log_error!("Ignoring malformed equalizer state: {}", e);
+                    return graphic_eq;
+                }
+            },
+            _ => return graphic_eq,
+        };
+
+        graphic_eq.presets.extend(state.user_presets);
+        if let Some(index) = state
+            .selected
+            .and_then(|name| graphic_eq.presets.iter().position(|p| p.name == name))
+        {
+            graphic_eq.selected_preset = index;
+            graphic_eq.gains = graphic_eq.presets[index].gains;
+        }
+        if let Some(gains) = state.gains {
+            graphic_eq.modified = gains != graphic_eq.gains;
+            graphic_eq.gains = gains.map(|g| g.clamp(-MAX_BAND_GAIN_DB, MAX_BAND_GAIN_DB));
+        }
+
+        log_debug!("Loaded equalizer preset {}", graphic_eq.preset_name());
+        graphic_eq
+    }
+
+    /// Write the selection and the user presets to the config directory
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::state_path().ok_or("No config directory available")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let state = EqState {
+            selected: Some(self.presets[self.selected_preset].name.clone()),
+            gains: Some(self.gains),
+            user_presets: self.presets[self.builtin_count..].to_vec(),
+        };
+        fs::write(path, serde_json::to_string_pretty(&state)?)?;
+        Ok(())
+    }
+
+    fn state_path() -> Option<PathBuf> {
+        get_config_dir().map(|dir| dir.join("equalizer.json"))
+    }
+
+    /// Name of the selected preset, marked when the bands were edited afterwards
+    pub fn preset_name(&self) -> String {
+        let name = &self.presets[self.selected_preset].name;
+        if self.modified {
+            format!("{}*", name)
+        } else {
+            name.clone()
+        }
+    }
+
+    /// Move the preset selection by `step`, wrapping around, and load its gains
+    pub fn cycle_preset(&mut self, step: isize) {
+        let count = self.presets.len() as isize;
+        self.selected_preset = (self.selected_preset as isize + step).rem_euclid(count) as usize;
+        self.gains = self.presets[self.selected_preset].gains;
+        self.modified = false;
+        log_debug!("Equalizer preset set to {}", self.preset_name());
+    }
+
+    /// Reset every band to 0 dB by selecting the "Flat" preset
+    pub fn reset(&mut self) {
+        self.selected_preset = 0;
+        self.gains = self.presets[0].gains;
+        self.modified = false;
+    }
+
+    /// Store the current gains as a user preset under `name` and select it.
+    /// A user preset of the same name is overwritten; a blank name, or one a
+    /// built-in preset has, gets a generated "User n" name instead.
+    pub fn save_as_user_preset(&mut self, name: &str) -> &str {
+        let name = name.trim();
+        let existing = self.presets.iter().position(|p| p.name == name);
+        match existing {
+            Some(index) if index >= self.builtin_count => {
+                self.presets[index].gains = self.gains;
+                self.selected_preset = index;
+            }
+            _ => {
+                let name = if name.is_empty() || existing.is_some() {
+                    let number = (1..)
+                        .find(|n| !self.presets.iter().any(|p| p.name == format!("User {}", n)))
+                        .unwrap_or(1);
+                    format!("User {}", number)
+                } else {
+                    name.to_string()
+                };
+                self.presets.push(EqPreset {
+                    name,
+                    gains: self.gains,
+                });
+                self.selected_preset = self.presets.len() - 1;
+            }
+        }
+        self.modified = false;
+        &self.presets[self.selected_preset].name
+    }
+
+    /// Delete the selected preset if it is a user preset
+    pub fn delete_user_preset(&mut self) -> bool {
+        if self.selected_preset < self.builtin_count {
+            return false;
+        }
+        self.presets.remove(self.selected_preset);
+        self.selected_preset -= 1;
+        self.modified = self.gains != self.presets[self.selected_preset].gains;
+        true
+    }
+
+    /// Adjusts the selected band by a delta and clamps it within the valid range
+    pub fn adjust_band(&mut self, delta: f32) {
+        let gain = &mut self.gains[self.selected_band];
+        *gain = (*gain + delta).clamp(-MAX_BAND_GAIN_DB, MAX_BAND_GAIN_DB);
+        self.modified = self.gains != self.presets[self.selected_preset].gains;
+        log_debug!(
+            "Equalizer band {} Hz adjusted to {} dB",
+            EQ_FREQUENCIES[self.selected_band],
+            self.gains[self.selected_band]
+        );
+    }
+
+    /// Select previous band, stopping at the lowest one
+    pub fn select_previous_band(&mut self) {
+        self.selected_band = self.selected_band.saturating_sub(1);
+    }
+
+    /// Select next band, stopping at the highest one
+    pub fn select_next_band(&mut self) {
+        self.selected_band = (self.selected_band + 1).min(EQ_BAND_COUNT - 1);
+    }
+}
@@ -19,6 +19,10 @@ pub struct Action {
 #[derive(Debug)]
 pub struct Keybindings {
     pub bindings: HashMap<KeyEvent, Action>,
+    /// Bindings that take precedence while the equalizer panel has focus
+    pub equalizer_bindings: HashMap<KeyEvent, Action>,
+    /// Bindings that take precedence while the play queue panel has focus
+    pub queue_bindings: HashMap<KeyEvent, Action>,
 }
 
 impl Keybindings {
@@ -51,6 +55,171 @@ impl Keybindings {
             },
         );
 
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char(']'), KeyModifiers::NONE),
+            Action {
+                name: "next_track".to_string(),
+                description: "Skip to the next track".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('['), KeyModifiers::NONE),
+            Action {
+                name: "prev_track".to_string(),
+                description: "Previous track (restarts after a few seconds)".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE),
+            Action {
+                name: "toggle_crossfade".to_string(),
+                description: "Toggle crossfade between tracks".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('R'), KeyModifiers::SHIFT),
+            Action {
+                name: "cycle_replay_gain".to_string(),
+                description: "Cycle ReplayGain mode (track/album/off)".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE),
+            Action {
+                name: "cycle_repeat".to_string(),
+                description: "Cycle repeat mode (off/one/all)".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE),
+            Action {
+                name: "cycle_shuffle".to_string(),
+                description: "Cycle shuffle (off/on/spread artists)".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE),
+            Action {
+                name: "page_up".to_string(),
+                description: "Move up a page".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE),
+            Action {
+                name: "page_down".to_string(),
+                description: "Move down a page".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE),
+            Action {
+                name: "select_first".to_string(),
+                description: "Jump to the top of the list".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT),
+            Action {
+                name: "select_last".to_string(),
+                description: "Jump to the bottom of the list".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE),
+            Action {
+                name: "center_on_playing".to_string(),
+                description: "Center the list on the playing track".to_string(),
+            },
+        );
+
+        // Library layout
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('v'), KeyModifiers::NONE),
+            Action {
+                name: "cycle_library_layout".to_string(),
+                description: "Switch between the track list, folder tree and album browser"
+                    .to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('S'), KeyModifiers::SHIFT),
+            Action {
+                name: "cycle_library_sort".to_string(),
+                description: "Cycle the track list order".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE),
+            Action {
+                name: "toggle_folder".to_string(),
+                description: "Expand/collapse the selected folder, or open the next column"
+                    .to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE),
+            Action {
+                name: "collapse_folder".to_string(),
+                description: "Collapse the folder around the selection, or go back a column"
+                    .to_string(),
+            },
+        );
+
+        // Search
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('/'), KeyModifiers::NONE),
+            Action {
+                name: "start_search".to_string(),
+                description: "Search the library".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE),
+            Action {
+                name: "next_match".to_string(),
+                description: "Jump to the next search match".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT),
+            Action {
+                name: "previous_match".to_string(),
+                description: "Jump to the previous search match".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('f'), KeyModifiers::NONE),
+            Action {
+                name: "toggle_search_filter".to_string(),
+                description: "List only the search matches".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+            Action {
+                name: "clear_search".to_string(),
+                description: "Clear the search".to_string(),
+            },
+        );
+
         // Navigation
         bindings.insert(
             KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE),
@@ -106,7 +275,7 @@ impl Keybindings {
             KeyEvent::new(KeyCode::Left, KeyModifiers::SHIFT),
             Action {
                 name: "pitch_left".to_string(),
-                description: "Adjust pitch down".to_string(),
+                description: "Transpose down a semitone".to_string(),
             },
         );
 
@@ -114,7 +283,24 @@ impl Keybindings {
             KeyEvent::new(KeyCode::Right, KeyModifiers::SHIFT),
             Action {
                 name: "pitch_right".to_string(),
-                description: "Adjust pitch up".to_string(),
+                description: "Transpose up a semitone".to_string(),
+            },
+        );
+
+        // Tempo controls
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char(','), KeyModifiers::NONE),
+            Action {
+                name: "tempo_down".to_string(),
+                description: "Decrease tempo".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('.'), KeyModifiers::NONE),
+            Action {
+                name: "tempo_up".to_string(),
+                description: "Increase tempo".to_string(),
             },
         );
 
@@ -183,7 +369,301 @@ impl Keybindings {
             },
         );
 
-        Self { bindings }
+        // Playlists
+        bindings.insert(
+            KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+            Action {
+                name: "next_view".to_string(),
+                description: "Show the next playlist (or the library)".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+            Action {
+                name: "previous_view".to_string(),
+                description: "Show the previous playlist (or the library)".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE),
+            Action {
+                name: "add_to_playlist".to_string(),
+                description: "Add selected track to the last shown playlist".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT),
+            Action {
+                name: "add_to_new_playlist".to_string(),
+                description: "Add selected track to a new playlist".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE),
+            Action {
+                name: "remove_from_playlist".to_string(),
+                description: "Remove selected entry from the playlist".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('X'), KeyModifiers::SHIFT),
+            Action {
+                name: "export_playlist".to_string(),
+                description: "Export the playlist as M3U8 and PLS".to_string(),
+            },
+        );
+
+        // Play queue
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE),
+            Action {
+                name: "add_to_queue".to_string(),
+                description: "Add selected track to the play queue".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('U'), KeyModifiers::SHIFT),
+            Action {
+                name: "play_next".to_string(),
+                description: "Play selected track next".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE),
+            Action {
+                name: "toggle_queue_focus".to_string(),
+                description: "Focus/unfocus the play queue".to_string(),
+            },
+        );
+
+        // Library scan report
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE),
+            Action {
+                name: "show_scan_report".to_string(),
+                description: "Show files the library scan skipped".to_string(),
+            },
+        );
+
+        // Equalizer panel
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE),
+            Action {
+                name: "toggle_equalizer_focus".to_string(),
+                description: "Focus/unfocus the equalizer".to_string(),
+            },
+        );
+
+        Self {
+            bindings,
+            equalizer_bindings: Self::equalizer_defaults(),
+            queue_bindings: Self::queue_defaults(),
+        }
+    }
+
+    /// Keys active while the equalizer panel is focused
+    fn equalizer_defaults() -> HashMap<KeyEvent, Action> {
+        let mut bindings = HashMap::new();
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Left, KeyModifiers::NONE),
+            Action {
+                name: "eq_band_previous".to_string(),
+                description: "Equalizer: select previous band".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Right, KeyModifiers::NONE),
+            Action {
+                name: "eq_band_next".to_string(),
+                description: "Equalizer: select next band".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Up, KeyModifiers::NONE),
+            Action {
+                name: "eq_gain_up".to_string(),
+                description: "Equalizer: boost band".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Down, KeyModifiers::NONE),
+            Action {
+                name: "eq_gain_down".to_string(),
+                description: "Equalizer: cut band".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('['), KeyModifiers::NONE),
+            Action {
+                name: "eq_preset_previous".to_string(),
+                description: "Equalizer: previous preset".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char(']'), KeyModifiers::NONE),
+            Action {
+                name: "eq_preset_next".to_string(),
+                description: "Equalizer: next preset".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE),
+            Action {
+                name: "eq_preset_save".to_string(),
+                description: "Equalizer: name and save bands as user preset".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE),
+            Action {
+                name: "eq_preset_delete".to_string(),
+                description: "Equalizer: delete user preset".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE),
+            Action {
+                name: "eq_reset".to_string(),
+                description: "Equalizer: reset to flat".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+            Action {
+                name: "toggle_equalizer_focus".to_string(),
+                description: "Equalizer: leave the panel".to_string(),
+            },
+        );
+
+        bindings
+    }
+
+    /// Keys active while the play queue panel is focused
+    fn queue_defaults() -> HashMap<KeyEvent, Action> {
+        let mut bindings = HashMap::new();
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Up, KeyModifiers::NONE),
+            Action {
+                name: "queue_select_previous".to_string(),
+                description: "Queue: select previous track".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE),
+            Action {
+                name: "queue_select_previous".to_string(),
+                description: "Queue: select previous track".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Down, KeyModifiers::NONE),
+            Action {
+                name: "queue_select_next".to_string(),
+                description: "Queue: select next track".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE),
+            Action {
+                name: "queue_select_next".to_string(),
+                description: "Queue: select next track".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT),
+            Action {
+                name: "queue_move_up".to_string(),
+                description: "Queue: move track up".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('K'), KeyModifiers::SHIFT),
+            Action {
+                name: "queue_move_up".to_string(),
+                description: "Queue: move track up".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Down, KeyModifiers::SHIFT),
+            Action {
+                name: "queue_move_down".to_string(),
+                description: "Queue: move track down".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('J'), KeyModifiers::SHIFT),
+            Action {
+                name: "queue_move_down".to_string(),
+                description: "Queue: move track down".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            Action {
+                name: "queue_play".to_string(),
+                description: "Queue: play track now".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE),
+            Action {
+                name: "queue_remove".to_string(),
+                description: "Queue: remove track".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE),
+            Action {
+                name: "queue_remove".to_string(),
+                description: "Queue: remove track".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Char('C'), KeyModifiers::SHIFT),
+            Action {
+                name: "queue_clear".to_string(),
+                description: "Queue: clear".to_string(),
+            },
+        );
+
+        bindings.insert(
+            KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+            Action {
+                name: "toggle_queue_focus".to_string(),
+                description: "Queue: leave the panel".to_string(),
+            },
+        );
+
+        bindings
     }
 
     /// Get an action by key event if it exists
@@ -191,11 +671,31 @@ impl Keybindings {
         self.bindings.get(key)
     }
 
+    /// Get an equalizer panel action by key event if it exists
+    pub fn get_equalizer_action(&self, key: &KeyEvent) -> Option<&Action> {
+        self.equalizer_bindings.get(key)
+    }
+
     /// Get all keybindings
     pub fn get_all_bindings(&self) -> &HashMap<KeyEvent, Action> {
         &self.bindings
     }
 
+    /// Get a play queue panel action by key event if it exists
+    pub fn get_queue_action(&self, key: &KeyEvent) -> Option<&Action> {
+        self.queue_bindings.get(key)
+    }
+
+    /// Get all keybindings of the play queue panel
+    pub fn get_queue_bindings(&self) -> &HashMap<KeyEvent, Action> {
+        &self.queue_bindings
+    }
+
+    /// Get all keybindings of the equalizer panel
+    pub fn get_equalizer_bindings(&self) -> &HashMap<KeyEvent, Action> {
+        &self.equalizer_bindings
+    }
+
     /// Get a pretty string representation of a key event
     pub fn key_event_to_string(key: &KeyEvent) -> String {
         let modifier_str = match key.modifiers {
@@ -214,6 +714,9 @@ impl Keybindings {
             KeyCode::Up => "↑".to_string(),
             KeyCode::Down => "↓".to_string(),
             KeyCode::Esc => "Esc".to_string(),
+            KeyCode::PageUp => "PageUp".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::PageDown => "PageDown".to_string(),
             _ => format!("{:?}", key.code),
         };
 
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_data_dir;
+use crate::controls::music_library::Track;
+use crate::log_error;
+
+/// Modification time and size identifying the current version of a file
+pub fn file_stamp(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    // Times before the epoch all count as the epoch
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    Ok((modified, metadata.len()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexEntry {
+    modified: u64,
+    size: u64,
+    track: Track,
+}
+
+/// The tracks of the library roots as found by the last scan, kept in
+/// `library.json` in the data directory so startup does not have to probe every
+/// file again. An entry stays valid as long as the file's modification time and
+/// size are unchanged.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LibraryIndex {
+    entries: HashMap<String, IndexEntry>,
+}
+
+impl LibraryIndex {
+    /// The index saved by a previous session
+    pub fn load() -> Option<Self> {
+        let content = fs::read_to_string(Self::index_path()?).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(index) => Some(index),
+            Err(e) => {
+                log_error!("Ignoring malformed library index: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::index_path().ok_or("No data directory available")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn index_path() -> Option<PathBuf> {
+        get_data_dir().map(|dir| dir.join("library.json"))
+    }
+
+    /// The indexed track for `path`, if the file still has the given stamp
+    pub fn get(&self, path: &Path, stamp: (u64, u64)) -> Option<&Track> {
+        let entry = self.entries.get(path.to_string_lossy().as_ref())?;
+        (stamp == (entry.modified, entry.size)).then_some(&entry.track)
+    }
+
+    /// When the file at `path` was added to the library, whatever its version
+    pub fn added(&self, path: &Path) -> Option<u64> {
+        self.entries
+            .get(path.to_string_lossy().as_ref())?
+            .track
+            .added
+    }
+
+    pub fn insert(&mut self, track: Track, (modified, size): (u64, u64)) {
+        self.entries.insert(
+            track.path.to_string_lossy().into_owned(),
+            IndexEntry {
+                modified,
+                size,
+                track,
+            },
+        );
+    }
+
+    /// Replace the entries at or below the `changed` paths by what a rescan of
+    /// them found
+    pub fn merge(&mut self, changed: &[PathBuf], rescanned: LibraryIndex) {
+        self.entries
+            .retain(|path, _| !changed.iter().any(|c| Path::new(path).starts_with(c)));
+        self.entries.extend(rescanned.entries);
+    }
+
+    /// All indexed tracks, in no particular order
+    pub fn tracks(&self) -> Vec<Track> {
+        self.entries
+            .values()
+            .map(|entry| entry.track.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,152 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::log_error;
+
+/// The directories the library is gathered from, merged into one collection,
+/// and which of the files below them it takes. Files are left out when they
+/// match one of the exclude patterns or lie deeper below their root than the
+/// depth limit. Patterns are globs matched against the path below the root,
+/// such as `**/Samples/**` or `*.tmp.mp3`.
+#[derive(Debug, Clone, Default)]
+pub struct LibraryRoots {
+    pub dirs: Vec<PathBuf>,
+    exclude: GlobSet,
+    /// Whether scans descend into symlinked directories and take symlinked
+    /// files; without it both are left out
+    pub follow_symlinks: bool,
+    /// How many directory levels below a root are scanned; files directly in
+    /// it are at depth 1
+    pub max_depth: Option<usize>,
+}
+
+impl LibraryRoots {
+    /// Roots with the given exclude patterns. Patterns that are not valid
+    /// globs are reported and ignored.
+    pub fn new(
+        dirs: Vec<PathBuf>,
+        exclude: &[String],
+        follow_symlinks: bool,
+        max_depth: Option<usize>,
+    ) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in exclude {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => log_error!("Ignoring exclude pattern {:?}: {}", pattern, e),
+            }
+        }
+        let exclude = builder.build().unwrap_or_else(|e| {
+            log_error!("Ignoring exclude patterns: {}", e);
+            GlobSet::empty()
+        });
+        Self {
+            dirs,
+            exclude,
+            follow_symlinks,
+            max_depth,
+        }
+    }
+
+    /// Index of the root a path lies below. Of nested roots the innermost
+    /// one counts.
+    pub fn root_of(&self, path: &Path) -> Option<usize> {
+        self.dirs
+            .iter()
+            .enumerate()
+            .filter(|(_, dir)| path.starts_with(dir))
+            .max_by_key(|(_, dir)| dir.components().count())
+            .map(|(root, _)| root)
+    }
+
+    /// Whether a file or directory below one of the roots belongs in the
+    /// library: it is neither excluded nor deeper than the depth limit
+    pub fn includes(&self, path: &Path) -> bool {
+        let Some(root) = self.root_of(path) else {
+            return false;
+        };
+        let relative = path.strip_prefix(&self.dirs[root]).unwrap_or(path);
+        let depth = relative.components().count();
+        self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+            && !self.exclude.is_match(relative)
+    }
+
+    /// Name of the root a path lies below, for telling where a track comes
+    /// from
+    pub fn source_of(&self, path: &Path) -> Option<Cow<'_, str>> {
+        let dir = &self.dirs[self.root_of(path)?];
+        Some(
+            dir.file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_else(|| dir.to_string_lossy()),
+        )
+    }
+
+    /// A path as shown to the user: below its root, and with several roots
+    /// led by the root's name
+    pub fn display_path<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        let Some(root) = self.root_of(path) else {
+            return Cow::Borrowed(path);
+        };
+        let relative = path.strip_prefix(&self.dirs[root]).unwrap_or(path);
+        match self.source_of(path) {
+            Some(source) if self.dirs.len() > 1 => Cow::Owned(Path::new(&*source).join(relative)),
+            _ => Cow::Borrowed(relative),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roots(dirs: &[&str], exclude: &[&str], max_depth: Option<usize>) -> LibraryRoots {
+        let exclude: Vec<String> = exclude.iter().map(|pattern| pattern.to_string()).collect();
+        LibraryRoots::new(
+            dirs.iter().map(PathBuf::from).collect(),
+            &exclude,
+            false,
+            max_depth,
+        )
+    }
+
+    #[test]
+    fn exclude_patterns_match_below_the_root() {
+        let roots = roots(
+            &["/home/Samples/music"],
+            &["**/Samples/**", "*.tmp.mp3"],
+            None,
+        );
+        assert!(roots.includes(Path::new("/home/Samples/music/a.mp3")));
+        assert!(!roots.includes(Path::new("/home/Samples/music/Drums/Samples/b.mp3")));
+        assert!(!roots.includes(Path::new("/home/Samples/music/Album/c.tmp.mp3")));
+        assert!(roots.includes(Path::new("/home/Samples/music/Album/c.mp3")));
+    }
+
+    #[test]
+    fn paths_outside_the_roots_are_not_included() {
+        let roots = roots(&["/music"], &[], None);
+        assert!(!roots.includes(Path::new("/other/a.mp3")));
+        assert!(!roots.includes(Path::new("/musical/a.mp3")));
+    }
+
+    #[test]
+    fn depth_limit_counts_from_the_innermost_root() {
+        let roots = roots(&["/music", "/music/inbox"], &[], Some(1));
+        assert!(roots.includes(Path::new("/music/a.mp3")));
+        assert!(!roots.includes(Path::new("/music/Album/a.mp3")));
+        assert!(roots.includes(Path::new("/music/inbox/b.mp3")));
+        assert_eq!(roots.root_of(Path::new("/music/inbox/b.mp3")), Some(1));
+    }
+
+    #[test]
+    fn invalid_patterns_are_ignored() {
+        let roots = roots(&["/music"], &["[", "*.wav"], None);
+        assert!(roots.includes(Path::new("/music/a.mp3")));
+        assert!(!roots.includes(Path::new("/music/a.wav")));
+    }
+}
@@ -0,0 +1,305 @@
+use crate::controls::music_library::{MusicLibrary, Track};
+
+/// The text of a track a search matched in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchField {
+    /// "Artist – Title", as listed
+    Name,
+    Album,
+    /// Path below the library root, led by the root's name when there are
+    /// several
+    Path,
+}
+
+/// A track matching the search, with the positions of the matched characters
+/// in the text they were found in
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    /// Index of the track in the library
+    pub index: usize,
+    pub field: MatchField,
+    /// Character positions of the matched characters, ascending
+    pub positions: Vec<usize>,
+    score: i32,
+}
+
+/// Incremental fuzzy search over the library. Every character typed narrows
+/// the matches; a track matches when the characters of the query appear in
+/// order in its name, album or path. Matches refer to tracks by their index in
+/// the library, so filtering the view does not change what gets played.
+#[derive(Debug, Default)]
+pub struct LibrarySearch {
+    pub query: String,
+    /// Whether typed keys go to the query
+    pub editing: bool,
+    /// Whether the library view lists only the matching tracks
+    pub filter: bool,
+    /// Matching tracks in library order
+    pub matches: Vec<SearchMatch>,
+    /// Library revision the matches were found in
+    revision: u64,
+    /// Cursor position when the search started, restored when it is cancelled
+    origin: usize,
+}
+
+impl LibrarySearch {
+    /// Start typing a new query
+    pub fn start(&mut self, cursor: usize) {
+        self.query.clear();
+        self.matches.clear();
+        self.editing = true;
+        self.origin = cursor;
+    }
+
+    /// Whether there is a query whose matches are shown
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty()
+    }
+
+    /// Add a character to the query, searching only the tracks that matched
+    /// the shorter query
+    pub fn push(&mut self, c: char, library: &MusicLibrary) {
+        let narrow = self.query.chars().any(|c| !c.is_whitespace());
+        self.query.push(c);
+        self.update(library, narrow);
+    }
+
+    /// Take the last character off the query
+    pub fn pop(&mut self, library: &MusicLibrary) {
+        self.query.pop();
+        self.update(library, false);
+    }
+
+    /// Stop typing, keeping the matches for `next_match` and the filter
+    pub fn confirm(&mut self) {
+        self.editing = false;
+        if self.query.is_empty() {
+            self.clear();
+        }
+    }
+
+    /// Drop the query and its matches. Returns the cursor position the search
+    /// started from.
+    pub fn cancel(&mut self) -> usize {
+        self.clear();
+        self.origin
+    }
+
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.matches.clear();
+        self.editing = false;
+        self.filter = false;
+    }
+
+    pub fn toggle_filter(&mut self) {
+        self.filter = !self.filter;
+    }
+
+    /// Search again when the library has changed since the last search
+    pub fn refresh(&mut self, library: &MusicLibrary) {
+        if self.revision != library.revision {
+            self.update(library, false);
+        }
+    }
+
+    /// Index of the best matching track
+    pub fn best(&self) -> Option<usize> {
+        self.matches
+            .iter()
+            .max_by_key(|m| (m.score, std::cmp::Reverse(m.index)))
+            .map(|m| m.index)
+    }
+
+    /// The first match after `from` in library order, wrapping around
+    pub fn next_match(&self, from: usize) -> Option<usize> {
+        self.matches
+            .iter()
+            .find(|m| m.index > from)
+            .or(self.matches.first())
+            .map(|m| m.index)
+    }
+
+    /// The last match before `from` in library order, wrapping around
+    pub fn previous_match(&self, from: usize) -> Option<usize> {
+        self.matches
+            .iter()
+            .rev()
+            .find(|m| m.index < from)
+            .or(self.matches.last())
+            .map(|m| m.index)
+    }
+
+    /// The match for a track, if it matches
+    pub fn match_for(&self, index: usize) -> Option<&SearchMatch> {
+        self.matches
+            .binary_search_by_key(&index, |m| m.index)
+            .ok()
+            .map(|position| &self.matches[position])
+    }
+
+    /// Whether the library view lists a track
+    pub fn shows(&self, index: usize) -> bool {
+        !self.filter || !self.is_active() || self.match_for(index).is_some()
+    }
+
+    /// Find the matches of the query, among the previous matches only when
+    /// `narrow` is set
+    fn update(&mut self, library: &MusicLibrary, narrow: bool) {
+        let pattern: Vec<char> = self
+            .query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect();
+        if pattern.is_empty() {
+            self.matches.clear();
+            self.revision = library.revision;
+            return;
+        }
+
+        // Earlier matches only carry over while the library is unchanged
+        let candidates: Vec<usize> = if narrow && self.revision == library.revision {
+            self.matches.iter().map(|m| m.index).collect()
+        } else {
+            (0..library.tracks.len()).collect()
+        };
+        self.matches = candidates
+            .into_iter()
+            .filter_map(|index| {
+                let track = library.tracks.get(index)?;
+                match_track(&pattern, track, library).map(|(field, score, positions)| SearchMatch {
+                    index,
+                    field,
+                    positions,
+                    score,
+                })
+            })
+            .collect();
+        self.revision = library.revision;
+    }
+}
+
+/// Text of a track a search looks at for a field
+pub fn field_text(track: &Track, field: MatchField, library: &MusicLibrary) -> String {
+    match field {
+        MatchField::Name => track.display_name(),
+        MatchField::Album => track.album.clone().unwrap_or_default(),
+        MatchField::Path => library
+            .roots
+            .display_path(&track.path)
+            .to_string_lossy()
+            .into_owned(),
+    }
+}
+
+/// The best match of the pattern among the searched texts of a track
+fn match_track(
+    pattern: &[char],
+    track: &Track,
+    library: &MusicLibrary,
+) -> Option<(MatchField, i32, Vec<usize>)> {
+    let mut best: Option<(MatchField, i32, Vec<usize>)> = None;
+    for field in [MatchField::Name, MatchField::Album, MatchField::Path] {
+        let Some((score, positions)) = fuzzy_match(pattern, &field_text(track, field, library))
+        else {
+            continue;
+        };
+        if best
+            .as_ref()
+            .is_none_or(|(_, best_score, _)| score > *best_score)
+        {
+            best = Some((field, score, positions));
+        }
+    }
+    best
+}
+
+/// Match the lowercase pattern against a text, fzf style: the characters have
+/// to appear in order, and matches that are tight and start at word
+/// boundaries score higher. Returns the score and the matched character
+/// positions.
+fn fuzzy_match(pattern: &[char], text: &str) -> Option<(i32, Vec<usize>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    // Find where the first complete match ends
+    let mut matched = 0;
+    let end = lower.iter().position(|&c| {
+        if c == pattern[matched] {
+            matched += 1;
+        }
+        matched == pattern.len()
+    })?;
+
+    // Walk back from there for the shortest match ending at the same place
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut remaining = pattern.len();
+    for i in (0..=end).rev() {
+        if lower[i] == pattern[remaining - 1] {
+            positions.push(i);
+            remaining -= 1;
+            if remaining == 0 {
+                break;
+            }
+        }
+    }
+    positions.reverse();
+
+    let mut score = 0;
+    for (k, &position) in positions.iter().enumerate() {
+        score += 16;
+        if position == 0 || !chars[position - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if k > 0 {
+            match position - positions[k - 1] - 1 {
+                0 => score += 8,
+                gap => score -= gap.min(8) as i32,
+            }
+        }
+    }
+    Some((score, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(query: &str) -> Vec<char> {
+        query.chars().collect()
+    }
+
+    #[test]
+    fn fuzzy_match_finds_shortest_match() {
+        let (_, positions) = fuzzy_match(&pattern("ab"), "a-xab").unwrap();
+        assert_eq!(positions, vec![3, 4]);
+        let (_, positions) = fuzzy_match(&pattern("abc"), "xaxbc").unwrap();
+        assert_eq!(positions, vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn fuzzy_match_ignores_case() {
+        let (_, positions) = fuzzy_match(&pattern("ab"), "ABBA").unwrap();
+        assert_eq!(positions, vec![0, 1]);
+    }
+
+    #[test]
+    fn fuzzy_match_needs_characters_in_order() {
+        assert!(fuzzy_match(&pattern("ba"), "ab").is_none());
+        assert!(fuzzy_match(&pattern("abc"), "ab").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_tight_matches_at_word_starts() {
+        let (tight, _) = fuzzy_match(&pattern("bl"), "blue").unwrap();
+        let (spread, _) = fuzzy_match(&pattern("bl"), "abxxl").unwrap();
+        assert!(tight > spread);
+        let (boundary, _) = fuzzy_match(&pattern("r"), "the road").unwrap();
+        let (inside, _) = fuzzy_match(&pattern("r"), "the bar").unwrap();
+        assert!(boundary > inside);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::event::{AccessKind, AccessMode, ModifyKind};
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+
+use crate::controls::library_index::LibraryIndex;
+use crate::controls::music_library::{scan_directories, MusicLibrary};
+use crate::{log_debug, log_error};
+
+/// How long the directory has to stay quiet before changes are applied, so a
+/// file that is still being written is not probed over and over
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Watches the library roots (inotify on Linux) and rescans files that are
+/// added, removed, renamed or rewritten while the app runs. Results are merged
+/// into the library, which hands them to the audio system like the result of
+/// the startup scan. Watching stops when this is dropped.
+pub struct LibraryWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl LibraryWatcher {
+    pub fn new(library: Arc<Mutex<MusicLibrary>>) -> Result<Self, Box<dyn Error>> {
+        let roots = library.lock().roots.clone();
+        let (tx, rx) = mpsc::channel();
+        // Links the scan does not follow are not watched through either
+        let config = Config::default().with_follow_symlinks(roots.follow_symlinks);
+        let mut watcher = RecommendedWatcher::new(tx, config)?;
+        for dir in &roots.dirs {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+        }
+
+        std::thread::spawn(move || watch(&library, &rx));
+        log_debug!("Watching {:?} for changes", roots.dirs);
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+/// Collect changed paths until the directory settles, then rescan them
+fn watch(library: &Mutex<MusicLibrary>, events: &Receiver<notify::Result<Event>>) {
+    let mut changed = BTreeSet::new();
+    loop {
+        let event = if changed.is_empty() {
+            events.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            events.recv_timeout(SETTLE_TIME)
+        };
+
+        match event {
+            Ok(Ok(event)) if changes_content(&event.kind) => changed.extend(event.paths),
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => log_error!("Library watcher error: {}", e),
+            Err(RecvTimeoutError::Timeout) => {
+                // Changes made during the startup scan wait for it to finish,
+                // so its result cannot overwrite them
+                MusicLibrary::wait_for_scan(library);
+                rescan(library, std::mem::take(&mut changed));
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Whether an event can add, remove or change a track. Opening and reading
+/// files, which playback and probing do all the time, does not.
+fn changes_content(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Create(_) | EventKind::Remove(_) => true,
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Modify(_) => true,
+        EventKind::Access(AccessKind::Close(AccessMode::Write)) => true,
+        _ => false,
+    }
+}
+
+/// Probe the changed paths outside the library lock and merge the result into
+/// the library and the saved index. Paths that no longer exist only remove
+/// what was there.
+fn rescan(library: &Mutex<MusicLibrary>, changed: BTreeSet<PathBuf>) {
+    // A directory rescan covers everything below it
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for path in changed {
+        if !paths.iter().any(|outer| path.starts_with(outer)) {
+            paths.push(path);
+        }
+    }
+
+    let roots = library.lock().roots.clone();
+    let existing: Vec<PathBuf> = paths.iter().filter(|path| path.exists()).cloned().collect();
+    let mut saved = LibraryIndex::load().unwrap_or_default();
+    let (index, skipped) = scan_directories(&existing, &roots, Some(&saved), |_, _| {});
+    let tracks = index.tracks();
+    saved.merge(&paths, index);
+    if let Err(e) = saved.save() {
+        log_error!("Failed to save library index: {}", e);
+    }
+
+    log_debug!(
+        "Watcher rescanned {} paths: {} tracks, {} skipped",
+        paths.len(),
+        tracks.len(),
+        skipped.len()
+    );
+    library.lock().merge_changes(&paths, tracks, skipped);
+}
@@ -0,0 +1,55 @@
+use std::ops::Range;
+
+/// Scroll position of the track panel, kept between frames so the list only
+/// scrolls when the cursor would leave it. The UI records how many rows fit,
+/// so paging knows how far a page goes.
+#[derive(Debug, Default)]
+pub struct ListViewport {
+    /// First visible row
+    pub offset: usize,
+    /// Rows that fit in the panel at the last render
+    pub height: usize,
+    /// Rows in the list at the last render
+    pub rows: usize,
+}
+
+impl ListViewport {
+    /// Rows one page moves, at least one
+    pub fn page(&self) -> usize {
+        self.height.max(1)
+    }
+
+    /// Scroll by a number of rows, staying within a list of `rows` rows
+    pub fn scroll(&mut self, delta: isize, rows: usize) {
+        self.offset = self
+            .offset
+            .saturating_add_signed(delta)
+            .min(rows.saturating_sub(self.height));
+    }
+
+    /// Scroll just far enough to bring the cursor row into a panel `height`
+    /// rows tall, and return the rows of the list that are visible
+    pub fn visible_rows(
+        &mut self,
+        rows: usize,
+        cursor_row: Option<usize>,
+        height: usize,
+    ) -> Range<usize> {
+        self.rows = rows;
+        self.height = height;
+        if let Some(cursor_row) = cursor_row {
+            if cursor_row < self.offset {
+                self.offset = cursor_row;
+            } else if cursor_row >= self.offset + height {
+                self.offset = cursor_row + 1 - height.max(1);
+            }
+        }
+        self.offset = self.offset.min(rows.saturating_sub(height));
+        self.offset..(self.offset + height).min(rows)
+    }
+
+    /// Scroll so a row sits in the middle of the panel
+    pub fn center_on(&mut self, row: usize) {
+        self.offset = row.saturating_sub(self.height / 2);
+    }
+}
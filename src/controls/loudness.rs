@@ -0,0 +1,361 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+use rodio::{Decoder, Source};
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_cache_dir;
+use crate::controls::library_index::file_stamp;
+use crate::controls::replay_gain::{ReplayGain, REFERENCE_LOUDNESS};
+use crate::{log_debug, log_error};
+
+/// Blocks quieter than this never count towards the integrated loudness
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Blocks this far below the ungated mean are left out as well
+const RELATIVE_GATE_LU: f64 = -10.0;
+/// The cache is written out after this many new results, or once
+/// `SAVE_INTERVAL` passed since the last write, and whenever the queue drains
+const SAVE_BATCH: usize = 20;
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Result of an EBU R128 loudness scan of one file
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Loudness {
+    pub integrated_lufs: f32,
+    pub peak: f32,
+}
+
+impl Loudness {
+    /// The track gain that brings this loudness to the ReplayGain reference level
+    pub fn replay_gain(&self) -> ReplayGain {
+        ReplayGain {
+            track_gain_db: Some(REFERENCE_LOUDNESS - self.integrated_lufs),
+            track_peak: Some(self.peak),
+            ..ReplayGain::default()
+        }
+    }
+}
+
+/// Second order IIR section in direct form I
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// The K-weighting filter of ITU-R BS.1770: a high shelf modelling the head
+/// followed by a high-pass, computed for any sample rate
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// Weight of a channel in the loudness sum; surround channels of a 5.1 layout
+/// count more and the LFE channel not at all
+fn channel_weight(channels: usize, channel: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (6, 4) | (6, 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn block_loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Measure the integrated loudness (EBU R128 / ITU-R BS.1770) and sample peak of
+/// an audio file
+pub fn scan(path: &Path) -> Result<Loudness, Box<dyn Error>> {
+    let source = Decoder::new(BufReader::new(File::open(path)?))?.convert_samples::<f32>();
+    let channels = source.channels().max(1) as usize;
+    let sample_rate = source.sample_rate();
+
+    let mut filters = vec![k_weighting(sample_rate); channels];
+    let weights: Vec<f64> = (0..channels)
+        .map(|channel| channel_weight(channels, channel))
+        .collect();
+
+    // Mean squares are gathered in 100 ms steps; a gating block spans four of them
+    let step_frames = (sample_rate as usize / 10).max(1);
+    let mut step_sums = vec![0.0; channels];
+    let mut step_filled = 0;
+    let mut steps = Vec::new();
+    let mut peak = 0f32;
+
+    for (index, sample) in source.enumerate() {
+        let channel = index % channels;
+        peak = peak.max(sample.abs());
+
+        let [shelf, high_pass] = &mut filters[channel];
+        let weighted = high_pass.process(shelf.process(sample as f64));
+        step_sums[channel] += weighted * weighted;
+
+        if channel == channels - 1 {
+            step_filled += 1;
+            if step_filled == step_frames {
+                let power = step_sums
+                    .iter()
+                    .zip(&weights)
+                    .map(|(sum, weight)| weight * sum / step_frames as f64)
+                    .sum::<f64>();
+                steps.push(power);
+                step_sums.iter_mut().for_each(|sum| *sum = 0.0);
+                step_filled = 0;
+            }
+        }
+    }
+
+    let blocks: Vec<f64> = steps
+        .windows(4)
+        .map(|w| w.iter().sum::<f64>() / 4.0)
+        .collect();
+    let mean_above = |threshold: f64| {
+        let gated: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&power| block_loudness(power) > threshold)
+            .collect();
+        (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+    };
+
+    let ungated = mean_above(ABSOLUTE_GATE_LUFS).ok_or("Track is too short or silent")?;
+    let threshold = block_loudness(ungated) + RELATIVE_GATE_LU;
+    let integrated = mean_above(threshold.max(ABSOLUTE_GATE_LUFS)).unwrap_or(ungated);
+
+    Ok(Loudness {
+        integrated_lufs: block_loudness(integrated) as f32,
+        peak,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    modified: u64,
+    size: u64,
+    /// None when the file could not be scanned
+    loudness: Option<Loudness>,
+}
+
+/// Scan results kept in `loudness.json` in the cache directory, valid as long as
+/// the file's modification time and size are unchanged. Failed scans are kept
+/// too, so broken files are not decoded again on every start.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LoudnessCache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl LoudnessCache {
+    fn load() -> Self {
+        let Some(Ok(content)) = Self::cache_path().map(fs::read_to_string) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_error!("Ignoring malformed loudness cache: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Write the cache to disk; the lock is only held while serializing
+    fn save(cache: &Mutex<Self>) -> Result<(), Box<dyn Error>> {
+        let path = Self::cache_path().ok_or("No cache directory available")?;
+        let content = serde_json::to_string(&*cache.lock())?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn cache_path() -> Option<PathBuf> {
+        get_cache_dir().map(|dir| dir.join("loudness.json"))
+    }
+
+    /// The cached result for the current version of a file: Some(None) when
+    /// its scan failed, None when it has not been scanned
+    fn get(&self, path: &Path) -> Option<Option<Loudness>> {
+        let entry = self.entries.get(path.to_string_lossy().as_ref())?;
+        let stamp = file_stamp(path).ok()?;
+        (stamp == (entry.modified, entry.size)).then_some(entry.loudness)
+    }
+
+    fn insert(&mut self, path: &Path, loudness: Option<Loudness>) {
+        if let Ok((modified, size)) = file_stamp(path) {
+            self.entries.insert(
+                path.to_string_lossy().into_owned(),
+                CacheEntry {
+                    modified,
+                    size,
+                    loudness,
+                },
+            );
+        }
+    }
+}
+
+struct ScanQueue {
+    pending: Mutex<VecDeque<PathBuf>>,
+    wakeup: Condvar,
+}
+
+/// Measures the loudness of files without ReplayGain tags on a background thread.
+///
+/// Results are cached on disk, so every file is only scanned once.
+pub struct LoudnessScanner {
+    cache: Arc<Mutex<LoudnessCache>>,
+    queue: Arc<ScanQueue>,
+    /// Set whenever a scan measured a file, cleared by `take_updated`
+    updated: Arc<AtomicBool>,
+}
+
+impl LoudnessScanner {
+    pub fn new() -> Self {
+        let scanner = Self {
+            cache: Arc::new(Mutex::new(LoudnessCache::load())),
+            queue: Arc::new(ScanQueue {
+                pending: Mutex::new(VecDeque::new()),
+                wakeup: Condvar::new(),
+            }),
+            updated: Arc::new(AtomicBool::new(false)),
+        };
+
+        let cache = Arc::clone(&scanner.cache);
+        let queue = Arc::clone(&scanner.queue);
+        let updated = Arc::clone(&scanner.updated);
+        std::thread::spawn(move || {
+            let mut unsaved = 0;
+            let mut last_save = Instant::now();
+            let save = |unsaved: &mut usize, last_save: &mut Instant| {
+                if let Err(e) = LoudnessCache::save(&cache) {
+                    log_error!("Failed to save loudness cache: {}", e);
+                }
+                *unsaved = 0;
+                *last_save = Instant::now();
+            };
+            loop {
+                let path = {
+                    let mut pending = queue.pending.lock();
+                    if pending.is_empty() && unsaved > 0 {
+                        None
+                    } else {
+                        while pending.is_empty() {
+                            queue.wakeup.wait(&mut pending);
+                        }
+                        pending.pop_front()
+                    }
+                };
+                // The queue drained with results still unsaved
+                let Some(path) = path else {
+                    save(&mut unsaved, &mut last_save);
+                    continue;
+                };
+                if cache.lock().get(&path).is_some() {
+                    continue;
+                }
+
+                let loudness = match scan(&path) {
+                    Ok(loudness) => {
+                        log_debug!(
+                            "Measured {:.1} LUFS, peak {:.3} for {:?}",
+                            loudness.integrated_lufs,
+                            loudness.peak,
+                            path
+                        );
+                        updated.store(true, Ordering::Relaxed);
+                        Some(loudness)
+                    }
+                    Err(e) => {
+                        log_error!("Loudness scan of {:?} failed: {}", path, e);
+                        None
+                    }
+                };
+                cache.lock().insert(&path, loudness);
+                unsaved += 1;
+                if unsaved >= SAVE_BATCH || last_save.elapsed() >= SAVE_INTERVAL {
+                    save(&mut unsaved, &mut last_save);
+                }
+            }
+        });
+
+        scanner
+    }
+
+    /// Cached loudness of a file, if it has been scanned successfully
+    pub fn get(&self, path: &Path) -> Option<Loudness> {
+        self.cache.lock().get(path).flatten()
+    }
+
+    /// Schedule a scan of a file that has no cached result, successful or not.
+    /// Urgent requests go before everything already waiting.
+    pub fn request(&self, path: &Path, urgent: bool) {
+        if self.cache.lock().get(path).is_some() {
+            return;
+        }
+        let mut pending = self.queue.pending.lock();
+        if urgent {
+            pending.retain(|waiting| waiting != path);
+            pending.push_front(path.to_path_buf());
+        } else {
+            pending.push_back(path.to_path_buf());
+        }
+        self.queue.wakeup.notify_one();
+    }
+
+    /// Whether new scan results arrived since the last call
+    pub fn take_updated(&self) -> bool {
+        self.updated.swap(false, Ordering::Relaxed)
+    }
+}
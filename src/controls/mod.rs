@@ -1,6 +1,27 @@
+pub mod album_browser;
 pub mod audio_engine;
+pub mod band_mapping;
+pub mod equalizer;
+pub mod fade;
+pub mod folder_tree;
+pub mod graphic_eq;
 pub mod keybindings;
+pub mod library_index;
+pub mod library_roots;
+pub mod library_search;
+pub mod library_watcher;
+pub mod list_viewport;
+pub mod loudness;
 pub mod music_library;
+pub mod native_bars;
+pub mod play_history;
+pub mod play_queue;
 pub mod playback_state;
+pub mod playlist;
+pub mod replay_gain;
+pub mod sample_tap;
+pub mod shuffle;
 pub mod sound_control;
 pub mod spectrum;
+pub mod time_stretch;
+pub mod tracked_source;
@@ -1,14 +1,146 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
+use parking_lot::{Condvar, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::FormatOptions;
 use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
 use symphonia::core::probe::Hint;
 use symphonia::default::get_probe;
 use walkdir::WalkDir;
 
+use crate::controls::library_index::{file_stamp, LibraryIndex};
+use crate::controls::library_roots::LibraryRoots;
+use crate::controls::replay_gain::ReplayGain;
+use crate::{log_debug, log_error};
+
+/// Supported audio file extensions
+const SUPPORTED_EXTENSIONS: [&str; 5] = ["mp3", "wav", "flac", "ogg", "m4a"];
+/// Number of tracks a running scan adds before the views regroup the library
+const SCAN_REVISION_BATCH: usize = 250;
+
+/// How the library pane lays out the tracks
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LibraryLayout {
+    /// One flat list of every track
+    #[default]
+    Tracks,
+    /// The folders of the music directories as a tree
+    Folders,
+    /// Album artists, their albums and the albums' tracks in three columns
+    Albums,
+}
+
+impl LibraryLayout {
+    pub fn next(self) -> Self {
+        match self {
+            LibraryLayout::Tracks => LibraryLayout::Folders,
+            LibraryLayout::Folders => LibraryLayout::Albums,
+            LibraryLayout::Albums => LibraryLayout::Tracks,
+        }
+    }
+}
+
+/// Orders the track list can be sorted in. Text is compared without regard to
+/// case and with numbers in it compared by value, so "2 - y" comes before
+/// "10 - x". Ties go by file name in the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LibrarySort {
+    /// By file name
+    #[default]
+    FileName,
+    /// By album artist, then album by year, disc and track number
+    Artist,
+    /// By album, then disc and track number
+    Album,
+    /// By disc and track number
+    TrackNumber,
+    /// Shortest first
+    Duration,
+    /// Most recently added first
+    DateAdded,
+}
+
+impl LibrarySort {
+    pub fn next(self) -> Self {
+        match self {
+            LibrarySort::FileName => LibrarySort::Artist,
+            LibrarySort::Artist => LibrarySort::Album,
+            LibrarySort::Album => LibrarySort::TrackNumber,
+            LibrarySort::TrackNumber => LibrarySort::Duration,
+            LibrarySort::Duration => LibrarySort::DateAdded,
+            LibrarySort::DateAdded => LibrarySort::FileName,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LibrarySort::FileName => "File Name",
+            LibrarySort::Artist => "Artist",
+            LibrarySort::Album => "Album",
+            LibrarySort::TrackNumber => "Track Number",
+            LibrarySort::Duration => "Duration",
+            LibrarySort::DateAdded => "Date Added",
+        }
+    }
+
+    /// Parse the order name used in the config file
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "file_name" => Some(LibrarySort::FileName),
+            "artist" => Some(LibrarySort::Artist),
+            "album" => Some(LibrarySort::Album),
+            "track_number" => Some(LibrarySort::TrackNumber),
+            "duration" => Some(LibrarySort::Duration),
+            "date_added" => Some(LibrarySort::DateAdded),
+            _ => None,
+        }
+    }
+
+    /// Compare two tracks by the keys of the order, one after the other
+    pub fn compare(self, a: &Track, b: &Track) -> Ordering {
+        let text = |a: Option<&String>, b: Option<&String>| match (a, b) {
+            (Some(a), Some(b)) => natural_cmp(a, b),
+            // Tracks without the tag go last
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        };
+        let (artist_a, artist_b) = (sort_artist(a), sort_artist(b));
+        let numbering = |track: &Track| {
+            (
+                track.disc_number.unwrap_or(1),
+                track.track_number.is_none(),
+                track.track_number,
+            )
+        };
+        let order = match self {
+            LibrarySort::FileName => Ordering::Equal,
+            LibrarySort::Artist => text(artist_a, artist_b)
+                .then_with(|| (a.year.is_none(), a.year).cmp(&(b.year.is_none(), b.year)))
+                .then_with(|| text(a.album.as_ref(), b.album.as_ref()))
+                .then_with(|| numbering(a).cmp(&numbering(b))),
+            LibrarySort::Album => text(a.album.as_ref(), b.album.as_ref())
+                .then_with(|| text(artist_a, artist_b))
+                .then_with(|| numbering(a).cmp(&numbering(b))),
+            LibrarySort::TrackNumber => numbering(a).cmp(&numbering(b)),
+            LibrarySort::Duration => {
+                (a.duration.is_none(), a.duration).cmp(&(b.duration.is_none(), b.duration))
+            }
+            LibrarySort::DateAdded => b.added.cmp(&a.added),
+        };
+        order.then_with(|| {
+            natural_cmp(&file_name(a), &file_name(b)).then_with(|| a.path.cmp(&b.path))
+        })
+    }
+}
+
 /// Manages the collection of audio tracks and track selection state.
 ///
 /// This struct is responsible for:
@@ -16,77 +148,258 @@ use walkdir::WalkDir;
 /// - Maintaining the collection of available tracks
 /// - Tracking which track is currently selected
 /// - Providing navigation between tracks (previous/next)
-#[derive(Default, Clone)]
+///
+/// Tracks from every root directory are merged into one collection. They are
+/// remembered in a `LibraryIndex` between sessions; the library starts from it
+/// when one exists and scans the roots in the background.
+/// The tracks are kept in the order of `sort`.
+#[derive(Default)]
 pub struct MusicLibrary {
     pub tracks: Vec<Track>,
     pub selected_index: Option<usize>,
     pub current_index: usize,
-    #[allow(dead_code)]
-    pub current_dir: PathBuf,
+    /// The directories the tracks come from
+    pub roots: LibraryRoots,
+    /// Order of the track list
+    pub sort: LibrarySort,
+    /// Files the last scan could not read
+    pub skipped: Vec<SkippedFile>,
+    /// Number of audio files the running scan has looked at, None when idle
+    pub scan_progress: Option<usize>,
+    /// Goes up whenever tracks are added, replaced or reordered, so state that
+    /// refers to tracks by index knows to refresh. A running scan raises it once
+    /// per `SCAN_REVISION_BATCH` tracks.
+    pub revision: u64,
+    /// Tracks and skipped files from a finished scan or watcher update, until
+    /// they have been taken over
+    pending_update: Option<ScanResult>,
+    /// Index in `tracks` of every track's path
+    positions: HashMap<PathBuf, usize>,
+    /// Signalled when the background scan has finished
+    scan_finished: Arc<Condvar>,
+    /// Tracks the running scan added or replaced since `revision` last went up
+    unannounced_tracks: usize,
+}
+
+/// A file left out of the library, and why
+#[derive(Clone, Debug)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: String,
 }
 
+/// Tracks found by a scan and the files it had to skip
+type ScanResult = (Vec<Track>, Vec<SkippedFile>);
+
 /// Represents an audio track with metadata and file information.
 ///
 /// Each track contains:
-/// - Basic metadata (title, optional artist)
+/// - Tag metadata (title, artist, album, numbering, year, genre, composer),
+///   with the file name as the title when the file has no tags
 /// - File path to the audio source
 /// - Duration information when available
-#[derive(Clone, Debug)]
+/// - ReplayGain values from the file's tags
+/// - When the file was added to the library
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Track {
     pub title: String,
-    #[allow(dead_code)]
     pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    pub composer: Option<String>,
     pub path: PathBuf,
     pub duration: Option<Duration>,
+    pub replay_gain: ReplayGain,
+    /// When the file first showed up in the library, in seconds since the
+    /// Unix epoch; its modification time at the time
+    #[serde(default)]
+    pub added: Option<u64>,
 }
 
 impl MusicLibrary {
-    /// Create a new MusicLibrary by loading tracks from the specified directory
-    pub fn new(path: PathBuf) -> Result<Self, Box<dyn Error>> {
-        let mut library = Self {
-            current_dir: path.clone(),
+    /// Create an empty MusicLibrary for the root directories; tracks are added
+    /// by `scan_in_background`
+    pub fn new(roots: LibraryRoots) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            roots,
             ..Default::default()
-        };
-        library.load_tracks(path)?;
-        Ok(library)
+        })
     }
 
-    /// Load tracks from a directory, supporting recursive search
-    pub fn load_tracks(&mut self, path: PathBuf) -> Result<(), Box<dyn Error>> {
-        // Clear existing tracks
-        self.tracks.clear();
+    /// Fill the library on a worker thread, first from the saved index and
+    /// then by scanning the roots. Newly probed tracks are appended as
+    /// soon as they are found, so indices of earlier tracks stay valid and they
+    /// can be played right away. The complete, sorted result is handed over
+    /// through `take_update`. Files that cannot be read are skipped and
+    /// listed in `skipped`.
+    pub fn scan_in_background(library: &Arc<Mutex<Self>>) {
+        let library = Arc::clone(library);
+        let roots = {
+            let mut library = library.lock();
+            library.scan_progress = Some(0);
+            library.roots.clone()
+        };
 
-        // Supported audio file extensions
-        let supported_extensions = ["mp3", "wav", "flac", "ogg", "m4a"];
+        std::thread::spawn(move || {
+            let previous = LibraryIndex::load();
+            if let Some(index) = &previous {
+                let mut library = library.lock();
+                library.tracks = index.tracks();
+                // The roots or exclude patterns may have changed since
+                library.tracks.retain(|track| roots.includes(&track.path));
+                library.sort_tracks();
+            }
 
-        // Walk through directory and load tracks
-        for entry in WalkDir::new(&path).into_iter().filter_map(|e| e.ok()) {
-            let file_path = entry.path().to_path_buf();
+            let (index, skipped) =
+                scan_directories(&roots.dirs, &roots, previous.as_ref(), |files, track| {
+                    let mut library = library.lock();
+                    library.scan_progress = Some(files);
+                    if let Some(track) = track {
+                        library.add_scanned_track(track.clone());
+                    }
+                });
+            if let Err(e) = index.save() {
+                log_error!("Failed to save library index: {}", e);
+            }
 
-            // Check if file has a supported audio extension
-            if let Some(ext) = file_path.extension() {
-                if supported_extensions.contains(&ext.to_str().unwrap_or("")) {
-                    // Try to get metadata
-                    let track = Track::from_file(file_path)?;
-                    self.add_track(track);
-                }
+            let mut library = library.lock();
+            library.scan_progress = None;
+            // Applying the update announces the remaining tracks
+            library.unannounced_tracks = 0;
+            library.pending_update = Some((index.tracks(), skipped));
+            library.scan_finished.notify_all();
+        });
+    }
+
+    /// Block until the running background scan, if any, has finished
+    pub fn wait_for_scan(library: &Mutex<Self>) {
+        let mut library = library.lock();
+        let scan_finished = Arc::clone(&library.scan_finished);
+        while library.scan_progress.is_some() {
+            scan_finished.wait(&mut library);
+        }
+    }
+
+    /// Add a track found by the running scan, replacing an older version of the
+    /// same file in place
+    fn add_scanned_track(&mut self, track: Track) {
+        match self.positions.get(&track.path) {
+            Some(&index) => self.tracks[index] = track,
+            None => {
+                self.positions.insert(track.path.clone(), self.tracks.len());
+                self.tracks.push(track);
             }
         }
+        // Regrouping the views for every file would keep them busy for the
+        // whole scan
+        self.unannounced_tracks += 1;
+        if self.unannounced_tracks >= SCAN_REVISION_BATCH {
+            self.unannounced_tracks = 0;
+            self.revision += 1;
+        }
+        if self.selected_index.is_none() {
+            self.selected_index = Some(0);
+        }
+    }
 
-        // Sort tracks by filename if needed
-        self.tracks.sort_by(|a, b| a.title.cmp(&b.title));
+    /// The new list of tracks once the background scan has finished or the
+    /// watcher has seen changes, to be applied with `replace_tracks`
+    pub fn take_update(&mut self) -> Option<Vec<Track>> {
+        let (tracks, skipped) = self.pending_update.take()?;
+        self.skipped = skipped;
+        Some(tracks)
+    }
+
+    /// Queue an update for paths that changed on disk: tracks and skipped files
+    /// at or below `changed` are replaced by what rescanning them found
+    pub fn merge_changes(
+        &mut self,
+        changed: &[PathBuf],
+        tracks: Vec<Track>,
+        skipped: Vec<SkippedFile>,
+    ) {
+        let (mut all_tracks, mut all_skipped) = self
+            .pending_update
+            .take()
+            .unwrap_or_else(|| (self.tracks.clone(), self.skipped.clone()));
+        let affected = |path: &Path| changed.iter().any(|c| path.starts_with(c));
+
+        // Rewritten files keep the date they were first added
+        let added: HashMap<PathBuf, u64> = all_tracks
+            .iter()
+            .filter(|track| affected(&track.path))
+            .filter_map(|track| Some((track.path.clone(), track.added?)))
+            .collect();
+        all_tracks.retain(|track| !affected(&track.path));
+        all_tracks.extend(tracks.into_iter().map(|mut track| {
+            if let Some(&added) = added.get(&track.path) {
+                track.added = Some(added);
+            }
+            track
+        }));
+        all_skipped.retain(|file| !affected(&file.path));
+        all_skipped.extend(skipped);
+        all_skipped.sort_by(|a, b| a.path.cmp(&b.path));
+
+        self.pending_update = Some((all_tracks, all_skipped));
+    }
+
+    /// Replace all tracks, keeping the cursor and selection on the same files.
+    /// Returns the new index of every old track, None for tracks that are gone.
+    pub fn replace_tracks(&mut self, tracks: Vec<Track>) -> Vec<Option<usize>> {
+        let old_tracks = std::mem::replace(&mut self.tracks, tracks);
+        self.sort_tracks();
+
+        let remap: Vec<Option<usize>> = old_tracks
+            .iter()
+            .map(|track| self.index_of(&track.path))
+            .collect();
+
+        let moved = |index: usize| remap.get(index).copied().flatten();
+        self.current_index = moved(self.current_index)
+            .unwrap_or(self.current_index.min(self.tracks.len().saturating_sub(1)));
+        self.selected_index = self
+            .selected_index
+            .and_then(moved)
+            .or((!self.tracks.is_empty()).then_some(0));
+
+        remap
+    }
+
+    /// Put the tracks in another order, keeping the cursor and selection on
+    /// the same files. Returns the new index of every track.
+    pub fn set_sort(&mut self, sort: LibrarySort) -> Vec<Option<usize>> {
+        self.sort = sort;
+        let tracks = self.tracks.clone();
+        self.replace_tracks(tracks)
+    }
+
+    /// Sort tracks in the library's order, index their paths and select the
+    /// first one if nothing is selected
+    fn sort_tracks(&mut self) {
+        let sort = self.sort;
+        self.tracks.sort_by(|a, b| sort.compare(a, b));
+        self.positions = self
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| (track.path.clone(), i))
+            .collect();
+        self.revision += 1;
 
         // Set initial selection to first track if tracks exist
-        if !self.tracks.is_empty() {
+        if self.selected_index.is_none() && !self.tracks.is_empty() {
             self.selected_index = Some(0);
         }
-
-        Ok(())
     }
 
-    /// Add a track to the library
-    pub fn add_track(&mut self, track: Track) {
-        self.tracks.push(track);
+    /// Index of the track with the given path
+    pub fn index_of(&self, path: &Path) -> Option<usize> {
+        self.positions.get(path).copied()
     }
 
     /// Select a track by index
@@ -121,33 +434,54 @@ impl MusicLibrary {
 impl Track {
     /// Create a new track from a file path and attempt to extract metadata
     pub fn from_file(path: PathBuf) -> Result<Self, Box<dyn Error>> {
-        let title = path
-            .file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or("Unknown")
-            .to_string();
+        let mut track = Self {
+            path,
+            ..Self::default()
+        };
+        track.extract_metadata()?;
 
-        let duration = Track::extract_duration(&path)?;
+        if track.title.is_empty() {
+            track.title = track
+                .path
+                .file_stem()
+                .and_then(|name| name.to_str())
+                .unwrap_or("Unknown")
+                .to_string();
+        }
 
-        Ok(Self {
-            title,
-            artist: None, // Metadata for artist can also be extracted
-            path,
-            duration,
-        })
+        Ok(track)
+    }
+
+    /// "Artist – Title", or just the title when the artist is unknown
+    pub fn display_name(&self) -> String {
+        match self.artist.as_ref().or(self.album_artist.as_ref()) {
+            Some(artist) => format!("{} – {}", artist, self.title),
+            None => self.title.clone(),
+        }
     }
 
-    /// Extract duration of the audio file using Symphonia
-    fn extract_duration(path: &PathBuf) -> Result<Option<Duration>, Box<dyn Error>> {
+    /// Read duration, tags and ReplayGain values of the audio file using Symphonia
+    fn extract_metadata(&mut self) -> Result<(), Box<dyn Error>> {
         // Prepare to probe the file
         let hint = Hint::new();
-        let src = std::fs::File::open(path)?;
+        let src = std::fs::File::open(&self.path)?;
         let mss = MediaSourceStream::new(Box::new(src), Default::default());
         let format_opts = FormatOptions::default();
         let metadata_opts = MetadataOptions::default();
 
         // Probe the file
-        let probed = get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+        let mut probed = get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+
+        // Tags can sit in front of the container (ID3v2) or inside it (Vorbis
+        // comments, MP4 atoms, FLAC); values found first win
+        if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+            self.read_tags(revision);
+            self.replay_gain.read_tags(revision);
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            self.read_tags(revision);
+            self.replay_gain.read_tags(revision);
+        }
 
         // Get the first audio track
         let track = probed
@@ -158,13 +492,261 @@ impl Track {
             .ok_or("No playable audio tracks found")?;
 
         // Calculate duration
-        if let Some(sample_rate) = track.codec_params.sample_rate {
-            if let Some(n_frames) = track.codec_params.n_frames {
-                let duration = Duration::from_secs_f64(n_frames as f64 / sample_rate as f64);
-                return Ok(Some(duration));
+        self.duration = match (track.codec_params.sample_rate, track.codec_params.n_frames) {
+            (Some(sample_rate), Some(n_frames)) => Some(Duration::from_secs_f64(
+                n_frames as f64 / sample_rate as f64,
+            )),
+            _ => None,
+        };
+
+        Ok(())
+    }
+
+    /// Collect the descriptive tags of one metadata revision, keeping values
+    /// that were already found in another revision
+    fn read_tags(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            let Some(key) = tag.std_key else { continue };
+            let value = tag.value.to_string();
+            // RIFF INFO strings keep their NUL terminators
+            let value = value.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            if value.is_empty() {
+                continue;
             }
+
+            let text = |slot: &mut Option<String>| {
+                slot.get_or_insert_with(|| value.to_string());
+            };
+            match key {
+                StandardTagKey::TrackTitle if self.title.is_empty() => {
+                    self.title = value.to_string()
+                }
+                StandardTagKey::Artist => text(&mut self.artist),
+                StandardTagKey::Album => text(&mut self.album),
+                StandardTagKey::AlbumArtist => text(&mut self.album_artist),
+                StandardTagKey::Genre => text(&mut self.genre),
+                StandardTagKey::Composer => text(&mut self.composer),
+                StandardTagKey::TrackNumber if self.track_number.is_none() => {
+                    self.track_number = parse_leading_number(value)
+                }
+                StandardTagKey::DiscNumber if self.disc_number.is_none() => {
+                    self.disc_number = parse_leading_number(value)
+                }
+                StandardTagKey::Date | StandardTagKey::OriginalDate if self.year.is_none() => {
+                    self.year = parse_leading_number(value).and_then(|y| i32::try_from(y).ok())
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Walk directories below the library roots and collect their audio files,
+/// leaving out what the roots exclude. Files that `previous` knows in their
+/// current version are taken from it instead of being probed again. Files and
+/// directories that cannot be read are skipped and reported. `progress` is
+/// called for every audio file with the number of files seen so far and the
+/// track, if it had to be probed.
+pub fn scan_directories(
+    dirs: &[PathBuf],
+    roots: &LibraryRoots,
+    previous: Option<&LibraryIndex>,
+    mut progress: impl FnMut(usize, Option<&Track>),
+) -> (LibraryIndex, Vec<SkippedFile>) {
+    let mut index = LibraryIndex::default();
+    let mut skipped = Vec::new();
+    let (mut files, mut reused, mut probed) = (0, 0, 0);
+
+    let entries = dirs.iter().flat_map(|dir| {
+        let mut walker = WalkDir::new(dir).follow_links(roots.follow_symlinks);
+        // The depth limit counts from the root, not from the directory
+        if let (Some(max_depth), Some(root)) = (roots.max_depth, roots.root_of(dir)) {
+            let depth = dir
+                .strip_prefix(&roots.dirs[root])
+                .map_or(0, |relative| relative.components().count());
+            walker = walker.max_depth(max_depth.saturating_sub(depth));
+        }
+        walker
+            .into_iter()
+            .filter_entry(|entry| roots.includes(entry.path()))
+            .map(move |entry| (dir, entry))
+    });
+    for (dir, entry) in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let reason = match e.io_error() {
+                    Some(io_error) => io_error.to_string(),
+                    None => e.to_string(),
+                };
+                let path = e.path().unwrap_or(dir).to_path_buf();
+                log_error!("Skipping {:?}: {}", path, reason);
+                skipped.push(SkippedFile { path, reason });
+                continue;
+            }
+        };
+        // Without following links, symlinked files are left out as well
+        if !roots.follow_symlinks && entry.path_is_symlink() {
+            continue;
+        }
+        let file_path = entry.path().to_path_buf();
+
+        // Check if file has a supported audio extension
+        let supported = file_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SUPPORTED_EXTENSIONS.contains(&ext));
+        if !supported {
+            continue;
         }
+        files += 1;
+        let stamp = match file_stamp(&file_path) {
+            Ok(stamp) => stamp,
+            Err(e) => {
+                progress(files, None);
+                log_error!("Skipping {:?}: {}", file_path, e);
+                skipped.push(SkippedFile {
+                    path: file_path,
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        // Files count as added when first seen, going by their modification time
+        let added = previous
+            .and_then(|previous| previous.added(&file_path))
+            .unwrap_or(stamp.0);
+
+        if let Some(track) = previous.and_then(|previous| previous.get(&file_path, stamp)) {
+            reused += 1;
+            let mut track = track.clone();
+            track.added = Some(added);
+            index.insert(track, stamp);
+            progress(files, None);
+            continue;
+        }
+        probed += 1;
+        match Track::from_file(file_path.clone()) {
+            Ok(mut track) => {
+                track.added = Some(added);
+                progress(files, Some(&track));
+                index.insert(track, stamp);
+            }
+            Err(e) => {
+                progress(files, None);
+                log_error!("Skipping {:?}: {}", file_path, e);
+                skipped.push(SkippedFile {
+                    path: file_path,
+                    reason: describe_probe_error(e.as_ref()),
+                });
+            }
+        }
+    }
+
+    log_debug!(
+        "Scanned {:?}: {} tracks unchanged, {} probed, {} skipped",
+        dirs,
+        reused,
+        probed,
+        skipped.len()
+    );
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    (index, skipped)
+}
+
+/// A readable reason for a file that could not be probed
+fn describe_probe_error(error: &(dyn Error + 'static)) -> String {
+    match error.downcast_ref::<SymphoniaError>() {
+        Some(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            "truncated or not an audio file".to_string()
+        }
+        Some(SymphoniaError::Unsupported(_)) => "unsupported or DRM-protected format".to_string(),
+        _ => error.to_string(),
+    }
+}
+
+/// Album artist of a track, or its artist when it has none
+fn sort_artist(track: &Track) -> Option<&String> {
+    track.album_artist.as_ref().or(track.artist.as_ref())
+}
+
+fn file_name(track: &Track) -> std::borrow::Cow<'_, str> {
+    track
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default()
+}
+
+/// Compare text without regard to case, with runs of digits compared by their
+/// value: "track 2" comes before "Track 10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut run = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        run.push(c);
+                    }
+                    run
+                };
+                let (x_run, y_run) = (digits(&mut a_chars), digits(&mut b_chars));
+                let (x_value, y_value) =
+                    (x_run.trim_start_matches('0'), y_run.trim_start_matches('0'));
+                let order = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+/// Parse the number at the start of values like "3/12" or "1999-04-01"
+fn parse_leading_number(value: &str) -> Option<u32> {
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        assert_eq!(natural_cmp("track 2", "track 10"), Ordering::Less);
+        assert_eq!(natural_cmp("Disc 10", "Disc 9"), Ordering::Greater);
+        assert_eq!(natural_cmp("01 Intro", "2 Outro"), Ordering::Less);
+        assert_eq!(natural_cmp("track", "track 1"), Ordering::Less);
+    }
+
+    #[test]
+    fn natural_cmp_ignores_case() {
+        assert_eq!(natural_cmp("abba", "Zed"), Ordering::Less);
+        assert_eq!(natural_cmp("Track 2", "track 10"), Ordering::Less);
+    }
 
-        Ok(None)
+    #[test]
+    fn natural_cmp_tells_apart_equal_looking_text() {
+        assert_ne!(natural_cmp("Abba", "abba"), Ordering::Equal);
+        assert_ne!(natural_cmp("track 02", "track 2"), Ordering::Equal);
+        assert_eq!(natural_cmp("track 2", "track 2"), Ordering::Equal);
     }
 }
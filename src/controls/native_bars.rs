@@ -0,0 +1,152 @@
+use std::time::Instant;
+
+use serde::Deserialize;
+
+/// How fast a bar falls under a gravity of 1, in bar heights per second squared
+const GRAVITY_ACCELERATION: f32 = 4.0;
+/// Longest time step the falloff takes, so a stalled UI does not drop every bar
+const MAX_STEP_SECONDS: f32 = 0.1;
+
+/// Settings of the cava-style bar visualizer, read from the `bars` section of the
+/// config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BarSettings {
+    /// Strength of the monstercat smoothing that lets loud bars lift their
+    /// neighbours, 0 switches it off
+    pub monstercat: f32,
+    /// How fast falling bars accelerate, 0 lets them follow the music directly
+    pub gravity: f32,
+    /// Colors from the bottom to the top of the bars, as "#rrggbb"
+    pub gradient: Vec<String>,
+}
+
+impl Default for BarSettings {
+    fn default() -> Self {
+        Self {
+            monstercat: 1.0,
+            gravity: 1.0,
+            gradient: vec!["#0099ff".to_string(), "#ff3399".to_string()],
+        }
+    }
+}
+
+impl BarSettings {
+    /// The gradient as RGB triples, skipping colors that do not parse
+    pub fn gradient_colors(&self) -> Vec<(u8, u8, u8)> {
+        let colors: Vec<(u8, u8, u8)> = self
+            .gradient
+            .iter()
+            .filter_map(|color| parse_hex_color(color))
+            .collect();
+        if colors.is_empty() {
+            return vec![(0x00, 0x99, 0xff), (0xff, 0x33, 0x99)];
+        }
+        colors
+    }
+}
+
+/// Parse "#rrggbb" or "rrggbb"
+fn parse_hex_color(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |range| u8::from_str_radix(&hex[range], 16).ok();
+    Some((channel(0..2)?, channel(2..4)?, channel(4..6)?))
+}
+
+/// Bar heights in the style of cava, computed from the visualizer bands.
+///
+/// Monstercat smoothing lets every bar lift its neighbours with a falloff, and
+/// gravity makes bars drop with increasing speed instead of following every dip
+/// of the music.
+pub struct NativeBars {
+    settings: BarSettings,
+    heights: Vec<f32>,
+    /// Height each bar started falling from, and how long it has been falling
+    fall_start: Vec<f32>,
+    fall_time: Vec<f32>,
+    last_update: Option<Instant>,
+}
+
+impl NativeBars {
+    pub fn new(settings: BarSettings) -> Self {
+        Self {
+            settings,
+            heights: Vec::new(),
+            fall_start: Vec::new(),
+            fall_time: Vec::new(),
+            last_update: None,
+        }
+    }
+
+    pub fn settings(&self) -> &BarSettings {
+        &self.settings
+    }
+
+    /// Advance the bars towards the band values
+    pub fn update(&mut self, bands: &[f32]) {
+        let now = Instant::now();
+        let step = self
+            .last_update
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32())
+            .min(MAX_STEP_SECONDS);
+        self.last_update = Some(now);
+
+        if self.heights.len() != bands.len() {
+            self.heights = vec![0.0; bands.len()];
+            self.fall_start = vec![0.0; bands.len()];
+            self.fall_time = vec![0.0; bands.len()];
+        }
+
+        let mut targets = bands.to_vec();
+        if self.settings.monstercat > 0.0 {
+            monstercat(&mut targets, self.settings.monstercat);
+        }
+
+        let acceleration = self.settings.gravity * GRAVITY_ACCELERATION;
+        for (i, target) in targets.into_iter().enumerate() {
+            if acceleration <= 0.0 || target >= self.heights[i] {
+                self.heights[i] = target;
+                self.fall_start[i] = target;
+                self.fall_time[i] = 0.0;
+                continue;
+            }
+
+            self.fall_time[i] += step;
+            let fallen = 0.5 * acceleration * self.fall_time[i].powi(2);
+            self.heights[i] = (self.fall_start[i] - fallen).max(target);
+        }
+    }
+
+    /// Bar heights from the last `update`, 0 to 1
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+}
+
+/// Raise every bar to at least its neighbours' heights divided by
+/// `(1.5 * strength)` per bar of distance, as cava's monstercat filter does
+fn monstercat(bars: &mut [f32], strength: f32) {
+    let falloff = (1.5 * strength).max(1.01);
+    for z in 0..bars.len() {
+        let source = bars[z];
+        let mut lifted = source;
+        for bar in bars[..z].iter_mut().rev() {
+            lifted /= falloff;
+            if lifted <= *bar {
+                break;
+            }
+            *bar = lifted;
+        }
+        lifted = source;
+        for bar in bars[z + 1..].iter_mut() {
+            lifted /= falloff;
+            if lifted <= *bar {
+                break;
+            }
+            *bar = lifted;
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use crate::controls::music_library::MusicLibrary;
+
+/// How many earlier tracks are remembered
+const MAX_HISTORY: usize = 500;
+
+/// Tracks that played before the current one, for stepping back through them,
+/// and the tracks stepped back past, which play again before playback carries
+/// on. Entries are kept as paths like the play queue so they survive library
+/// rescans; entries whose file is not in the library are passed over.
+#[derive(Debug, Default)]
+pub struct PlayHistory {
+    /// Earlier tracks, the most recent last
+    played: Vec<PathBuf>,
+    /// Tracks left by stepping back, the one to return to first last
+    stepped_back: Vec<PathBuf>,
+}
+
+impl PlayHistory {
+    /// Remember a track that playback moved on from
+    pub fn push(&mut self, path: &Path) {
+        if self.played.len() >= MAX_HISTORY {
+            self.played.remove(0);
+        }
+        self.played.push(path.to_path_buf());
+    }
+
+    /// Forget the tracks stepped back past, once the user picks what plays
+    pub fn clear_stepped_back(&mut self) {
+        self.stepped_back.clear();
+    }
+
+    /// Library index of the most recent earlier track that is in the library,
+    /// taking it off the history. `current` is remembered to return to.
+    pub fn step_back(&mut self, library: &MusicLibrary, current: Option<&Path>) -> Option<usize> {
+        while let Some(path) = self.played.pop() {
+            if let Some(index) = library.index_of(&path) {
+                if let Some(current) = current {
+                    self.stepped_back.push(current.to_path_buf());
+                }
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Library index of the track to return to after stepping back, None when
+    /// playback has not stepped back or has caught up again
+    pub fn next(&self, library: &MusicLibrary) -> Option<usize> {
+        self.stepped_back
+            .iter()
+            .rev()
+            .find_map(|path| library.index_of(path))
+    }
+
+    /// Remove the track `next` chose once it starts, along with the entries
+    /// after it that could not be played. Returns false when the track did not
+    /// come from the history.
+    pub fn take(&mut self, library: &MusicLibrary, index: usize) -> bool {
+        let position = self
+            .stepped_back
+            .iter()
+            .rposition(|path| library.index_of(path).is_some());
+        match position {
+            Some(position) if library.index_of(&self.stepped_back[position]) == Some(index) => {
+                self.stepped_back.truncate(position);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::music_library::Track;
+
+    fn library(paths: &[&str]) -> MusicLibrary {
+        let mut library = MusicLibrary::default();
+        library.replace_tracks(
+            paths
+                .iter()
+                .map(|path| Track {
+                    path: PathBuf::from(path),
+                    ..Track::default()
+                })
+                .collect(),
+        );
+        library
+    }
+
+    fn index(library: &MusicLibrary, path: &str) -> usize {
+        library.index_of(Path::new(path)).unwrap()
+    }
+
+    #[test]
+    fn step_back_skips_missing_tracks_and_remembers_the_current_one() {
+        let library = library(&["/a.mp3", "/c.mp3"]);
+        let mut history = PlayHistory::default();
+        history.push(Path::new("/a.mp3"));
+        history.push(Path::new("/gone.mp3"));
+        assert_eq!(
+            history.step_back(&library, Some(Path::new("/c.mp3"))),
+            Some(index(&library, "/a.mp3"))
+        );
+        assert_eq!(history.step_back(&library, None), None);
+        assert_eq!(history.next(&library), Some(index(&library, "/c.mp3")));
+    }
+
+    #[test]
+    fn take_returns_along_the_stepped_back_tracks() {
+        let library = library(&["/a.mp3", "/b.mp3", "/c.mp3"]);
+        let mut history = PlayHistory::default();
+        history.push(Path::new("/a.mp3"));
+        history.push(Path::new("/b.mp3"));
+        history.step_back(&library, Some(Path::new("/c.mp3")));
+        history.step_back(&library, Some(Path::new("/b.mp3")));
+
+        assert!(!history.take(&library, index(&library, "/c.mp3")));
+        assert!(history.take(&library, index(&library, "/b.mp3")));
+        assert_eq!(history.next(&library), Some(index(&library, "/c.mp3")));
+        assert!(history.take(&library, index(&library, "/c.mp3")));
+        assert_eq!(history.next(&library), None);
+    }
+
+    #[test]
+    fn take_drops_missing_tracks_after_the_chosen_one() {
+        let library = library(&["/a.mp3", "/b.mp3", "/c.mp3"]);
+        let mut history = PlayHistory::default();
+        history.push(Path::new("/a.mp3"));
+        history.push(Path::new("/b.mp3"));
+        history.step_back(&library, Some(Path::new("/c.mp3")));
+        history.step_back(&library, Some(Path::new("/gone.mp3")));
+
+        assert_eq!(history.next(&library), Some(index(&library, "/c.mp3")));
+        assert!(history.take(&library, index(&library, "/c.mp3")));
+        assert_eq!(history.next(&library), None);
+    }
+}
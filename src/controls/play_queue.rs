@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+use crate::controls::music_library::MusicLibrary;
+
+/// Tracks lined up to play before playback continues in library or playlist
+/// order. Entries are kept as paths so they survive library rescans; entries
+/// whose file is not in the library are passed over.
+#[derive(Debug, Default)]
+pub struct PlayQueue {
+    pub entries: Vec<PathBuf>,
+    /// Highlighted entry while the queue panel has focus
+    pub selected: usize,
+}
+
+impl PlayQueue {
+    /// Add a track to the end of the queue
+    pub fn push(&mut self, path: &Path) {
+        self.entries.push(path.to_path_buf());
+    }
+
+    /// Put a track in front of everything already queued
+    pub fn push_next(&mut self, path: &Path) {
+        self.entries.insert(0, path.to_path_buf());
+        // Keep the selection on the same entry
+        if self.entries.len() > 1 {
+            self.selected += 1;
+        }
+    }
+
+    /// Take the selected entry out of the queue
+    pub fn remove_selected(&mut self) -> Option<PathBuf> {
+        if self.selected >= self.entries.len() {
+            return None;
+        }
+        let path = self.entries.remove(self.selected);
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        Some(path)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Move the selected entry one place earlier, keeping it selected
+    pub fn move_selected_up(&mut self) {
+        if self.selected > 0 && self.selected < self.entries.len() {
+            self.entries.swap(self.selected, self.selected - 1);
+            self.selected -= 1;
+        }
+    }
+
+    /// Move the selected entry one place later, keeping it selected
+    pub fn move_selected_down(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.entries.swap(self.selected, self.selected + 1);
+            self.selected += 1;
+        }
+    }
+
+    /// The first entry that is in the library, with its index in the library
+    pub fn next(&self, library: &MusicLibrary) -> Option<(usize, usize)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .find_map(|(position, path)| library.index_of(path).map(|index| (position, index)))
+    }
+
+    /// Remove the entry `next` chose once its track starts, along with the
+    /// entries before it that could not be played. Returns false when the
+    /// track did not come from the queue.
+    pub fn take(&mut self, library: &MusicLibrary, index: usize) -> bool {
+        match self.next(library) {
+            Some((position, next_index)) if next_index == index => {
+                self.entries.drain(..=position);
+                self.selected = self.selected.saturating_sub(position + 1);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::music_library::Track;
+
+    fn library(paths: &[&str]) -> MusicLibrary {
+        let mut library = MusicLibrary::default();
+        library.replace_tracks(
+            paths
+                .iter()
+                .map(|path| Track {
+                    path: PathBuf::from(path),
+                    ..Track::default()
+                })
+                .collect(),
+        );
+        library
+    }
+
+    fn queue(paths: &[&str]) -> PlayQueue {
+        let mut queue = PlayQueue::default();
+        for path in paths {
+            queue.push(Path::new(path));
+        }
+        queue
+    }
+
+    #[test]
+    fn take_removes_the_next_entry_and_the_missing_ones_before_it() {
+        let library = library(&["/a.mp3", "/b.mp3"]);
+        let mut queue = queue(&["/gone.mp3", "/b.mp3", "/a.mp3"]);
+        let b = library.index_of(Path::new("/b.mp3")).unwrap();
+        assert_eq!(queue.next(&library), Some((1, b)));
+        assert!(queue.take(&library, b));
+        assert_eq!(queue.entries, vec![PathBuf::from("/a.mp3")]);
+    }
+
+    #[test]
+    fn take_leaves_the_queue_for_other_tracks() {
+        let library = library(&["/a.mp3", "/b.mp3"]);
+        let mut queue = queue(&["/b.mp3"]);
+        let a = library.index_of(Path::new("/a.mp3")).unwrap();
+        assert!(!queue.take(&library, a));
+        assert_eq!(queue.entries.len(), 1);
+    }
+
+    #[test]
+    fn take_keeps_the_selection_on_the_same_entry() {
+        let library = library(&["/a.mp3", "/b.mp3", "/c.mp3"]);
+        let mut queue = queue(&["/a.mp3", "/b.mp3", "/c.mp3"]);
+        queue.selected = 2;
+        assert!(queue.take(&library, library.index_of(Path::new("/a.mp3")).unwrap()));
+        assert_eq!(queue.entries[queue.selected], PathBuf::from("/c.mp3"));
+    }
+}
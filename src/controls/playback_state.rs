@@ -7,12 +7,18 @@ use std::time::Duration;
 /// - Selected track index
 /// - Elapsed time within the current track
 /// - Total duration of the current track
+/// - Whether tracks crossfade into each other, and for how long
+/// - The repeat and shuffle modes that decide which track plays next
 #[derive(Default, Clone)]
 pub struct PlaybackState {
     pub status: PlaybackStatus,
     pub current_track: Option<usize>,
     pub elapsed: Duration,
     pub total_time: Duration,
+    pub crossfade_enabled: bool,
+    pub crossfade: Duration,
+    pub repeat: RepeatMode,
+    pub shuffle: ShuffleMode,
 }
 
 /// Represents the current playback status of the audio system.
@@ -29,6 +35,86 @@ pub enum PlaybackStatus {
     Paused,
 }
 
+/// What auto-advance does once the last track has played
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RepeatMode {
+    /// Playback stops at the end
+    Off,
+    /// The current track plays again and again
+    One,
+    /// Playback starts over from the beginning
+    #[default]
+    All,
+}
+
+impl RepeatMode {
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::One,
+            RepeatMode::One => RepeatMode::All,
+            RepeatMode::All => RepeatMode::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "Off",
+            RepeatMode::One => "One",
+            RepeatMode::All => "All",
+        }
+    }
+
+    /// Parse the mode name used in the config file
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(RepeatMode::Off),
+            "one" => Some(RepeatMode::One),
+            "all" => Some(RepeatMode::All),
+            _ => None,
+        }
+    }
+}
+
+/// Whether auto-advance follows the list order or a shuffled one
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShuffleMode {
+    /// Tracks play in list order
+    #[default]
+    Off,
+    /// Every track plays once, in random order, before any plays again
+    On,
+    /// Like `On`, but tracks of the same artist are kept apart
+    SpreadArtists,
+}
+
+impl ShuffleMode {
+    pub fn next(self) -> Self {
+        match self {
+            ShuffleMode::Off => ShuffleMode::On,
+            ShuffleMode::On => ShuffleMode::SpreadArtists,
+            ShuffleMode::SpreadArtists => ShuffleMode::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShuffleMode::Off => "Off",
+            ShuffleMode::On => "On",
+            ShuffleMode::SpreadArtists => "Spread Artists",
+        }
+    }
+
+    /// Parse the mode name used in the config file
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(ShuffleMode::Off),
+            "on" => Some(ShuffleMode::On),
+            "spread_artists" => Some(ShuffleMode::SpreadArtists),
+            _ => None,
+        }
+    }
+}
+
 impl PlaybackState {
     pub fn start(&mut self, track_index: usize, total_duration: Duration) {
         self.current_track = Some(track_index);
@@ -0,0 +1,574 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_data_dir;
+use crate::controls::music_library::{MusicLibrary, Track};
+use crate::{log_debug, log_error};
+
+/// One line of a playlist: the file, and what the playlist says about it
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub path: PathBuf,
+    /// Title as written by `#EXTINF` or `TitleN=`, or the track's name when added
+    pub title: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+impl PlaylistEntry {
+    pub fn from_track(track: &Track) -> Self {
+        Self {
+            path: track.path.clone(),
+            title: Some(track.display_name()),
+            duration: track.duration,
+        }
+    }
+
+    /// The title, or the file name when the playlist did not give one
+    pub fn display_name(&self) -> String {
+        match &self.title {
+            Some(title) => title.clone(),
+            None => self
+                .path
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A named list of files, saved as JSON in the `playlists` data directory and
+/// exchangeable with other players as M3U/M3U8 or PLS
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Playlist {
+    pub name: String,
+    pub entries: Vec<PlaylistEntry>,
+    /// The M3U/M3U8 or PLS file the playlist was imported from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
+}
+
+impl Playlist {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            entries: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// Read an M3U/M3U8 or PLS file. Relative paths are resolved against the
+    /// directory of the playlist file.
+    pub fn import(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read(path)?;
+        let content = String::from_utf8_lossy(&content);
+        let base = path.parent().unwrap_or(Path::new("."));
+        let entries = match extension(path).as_str() {
+            "m3u" | "m3u8" => parse_m3u(&content, base),
+            "pls" => parse_pls(&content, base),
+            other => return Err(format!("Unsupported playlist format: {:?}", other).into()),
+        };
+
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Imported".to_string());
+        let source = Some(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        Ok(Self {
+            name,
+            entries,
+            source,
+        })
+    }
+
+    /// Write the playlist as M3U8 or PLS, depending on the extension of `path`.
+    /// Files below the playlist's directory are written as relative paths.
+    pub fn export(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let base = path.parent().unwrap_or(Path::new("."));
+        let content = match extension(path).as_str() {
+            "m3u" | "m3u8" => self.to_m3u(base),
+            "pls" => self.to_pls(base),
+            other => return Err(format!("Unsupported playlist format: {:?}", other).into()),
+        };
+        if !base.as_os_str().is_empty() {
+            fs::create_dir_all(base)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn to_m3u(&self, base: &Path) -> String {
+        let mut content = String::from("#EXTM3U\n");
+        for entry in &self.entries {
+            content.push_str(&format!(
+                "#EXTINF:{},{}\n{}\n",
+                length_seconds(entry.duration),
+                entry.display_name(),
+                relative_location(&entry.path, base)
+            ));
+        }
+        content
+    }
+
+    fn to_pls(&self, base: &Path) -> String {
+        let mut content = String::from("[playlist]\n");
+        for (i, entry) in self.entries.iter().enumerate() {
+            let number = i + 1;
+            content.push_str(&format!(
+                "File{number}={}\nTitle{number}={}\nLength{number}={}\n",
+                relative_location(&entry.path, base),
+                entry.display_name(),
+                length_seconds(entry.duration)
+            ));
+        }
+        content.push_str(&format!(
+            "NumberOfEntries={}\nVersion=2\n",
+            self.entries.len()
+        ));
+        content
+    }
+}
+
+/// All saved playlists, and which of them the track list shows and playback
+/// follows
+#[derive(Debug, Default)]
+pub struct Playlists {
+    pub playlists: Vec<Playlist>,
+    /// Playlist shown in the track list, None for the library
+    pub view: Option<usize>,
+    /// Highlighted entry of the shown playlist
+    pub selected_entry: usize,
+    /// Playlist and entry playback follows, None while playing the library
+    pub playing: Option<(usize, usize)>,
+    /// Playlist that tracks are added to: the one shown last
+    pub target: Option<usize>,
+}
+
+impl Playlists {
+    /// Load the saved playlists, in order of their names
+    pub fn load() -> Self {
+        let mut playlists = Vec::new();
+        if let Some(entries) = playlists_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if extension(&path) != "json" {
+                    continue;
+                }
+                let playlist = fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| {
+                        serde_json::from_str::<Playlist>(&content).map_err(|e| e.to_string())
+                    });
+                match playlist {
+                    Ok(playlist) => playlists.push(playlist),
+                    Err(e) => log_error!("Ignoring playlist {:?}: {}", path, e),
+                }
+            }
+        }
+        playlists.sort_by(|a, b| a.name.cmp(&b.name));
+        log_debug!("Loaded {} playlists", playlists.len());
+
+        Self {
+            playlists,
+            ..Default::default()
+        }
+    }
+
+    fn save(&self, index: usize) {
+        let Some(playlist) = self.playlists.get(index) else {
+            return;
+        };
+        let result = playlist_path(&playlist.name, "json")
+            .ok_or_else(|| "No data directory available".into())
+            .and_then(|path| -> Result<(), Box<dyn Error>> {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, serde_json::to_string_pretty(playlist)?)?;
+                Ok(())
+            });
+        if let Err(e) = result {
+            log_error!("Failed to save playlist {:?}: {}", playlist.name, e);
+        }
+    }
+
+    /// Add and save a playlist under a name no other playlist has yet
+    pub fn add(&mut self, mut playlist: Playlist) -> usize {
+        playlist.name = self.unique_name(&playlist.name);
+        self.playlists.push(playlist);
+        let index = self.playlists.len() - 1;
+        self.save(index);
+        index
+    }
+
+    /// Import an M3U/M3U8 or PLS file as a new playlist. A playlist imported
+    /// from the same file before has its entries replaced instead, so
+    /// importing on every start does not pile up copies.
+    pub fn import(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let playlist = Playlist::import(path)?;
+        log_debug!(
+            "Imported {} entries from {:?}",
+            playlist.entries.len(),
+            path
+        );
+        let existing = self
+            .playlists
+            .iter()
+            .position(|p| p.source.is_some() && p.source == playlist.source);
+        match existing {
+            Some(index) => {
+                self.playlists[index].entries = playlist.entries;
+                self.save(index);
+            }
+            None => {
+                self.add(playlist);
+            }
+        }
+        Ok(())
+    }
+
+    /// Export the shown playlist as M3U8 and PLS beside its saved copy
+    pub fn export_shown(&self) -> Result<(), Box<dyn Error>> {
+        let playlist = self
+            .view
+            .and_then(|index| self.playlists.get(index))
+            .ok_or("No playlist shown")?;
+        for format in ["m3u8", "pls"] {
+            let path =
+                playlist_path(&playlist.name, format).ok_or("No data directory available")?;
+            playlist.export(&path)?;
+            log_debug!("Exported playlist to {:?}", path);
+        }
+        Ok(())
+    }
+
+    /// A name whose file no other playlist's file clashes with once unsafe
+    /// characters are replaced, compared regardless of case as some file
+    /// systems do. The saved file keeps the name as given.
+    fn unique_name(&self, name: &str) -> String {
+        let taken = |candidate: &str| {
+            let key = file_name(candidate).to_lowercase();
+            self.playlists
+                .iter()
+                .any(|p| file_name(&p.name).to_lowercase() == key)
+        };
+        if !taken(name) {
+            return name.to_string();
+        }
+        (2..)
+            .map(|n| format!("{} ({})", name, n))
+            .find(|candidate| !taken(candidate))
+            .unwrap_or_default()
+    }
+
+    /// Show the next playlist, or the library after the last one
+    pub fn view_next(&mut self) {
+        self.view = match self.view {
+            None if !self.playlists.is_empty() => Some(0),
+            Some(index) if index + 1 < self.playlists.len() => Some(index + 1),
+            _ => None,
+        };
+        self.view_changed();
+    }
+
+    /// Show the previous playlist, or the library before the first one
+    pub fn view_previous(&mut self) {
+        self.view = match self.view {
+            None => self.playlists.len().checked_sub(1),
+            Some(index) => index.checked_sub(1),
+        };
+        self.view_changed();
+    }
+
+    fn view_changed(&mut self) {
+        self.selected_entry = match (self.view, self.playing) {
+            (Some(view), Some((playing, entry))) if view == playing => entry,
+            _ => 0,
+        };
+        if self.view.is_some() {
+            self.target = self.view;
+        }
+    }
+
+    /// The playlist shown in the track list
+    pub fn shown(&self) -> Option<&Playlist> {
+        self.view.and_then(|index| self.playlists.get(index))
+    }
+
+    /// Select the previous entry of the shown playlist, wrapping around
+    pub fn select_previous_entry(&mut self) {
+        let len = self.shown().map_or(0, |playlist| playlist.entries.len());
+        if len > 0 {
+            self.selected_entry = (self.selected_entry + len - 1) % len;
+        }
+    }
+
+    /// Select the next entry of the shown playlist, wrapping around
+    pub fn select_next_entry(&mut self) {
+        let len = self.shown().map_or(0, |playlist| playlist.entries.len());
+        if len > 0 {
+            self.selected_entry = (self.selected_entry + 1) % len;
+        }
+    }
+
+    /// Add a track to the target playlist, creating one if there is none yet
+    pub fn add_to_target(&mut self, track: &Track) {
+        let index = match self.target.filter(|&index| index < self.playlists.len()) {
+            Some(index) => index,
+            None => self.add(Playlist::new("Playlist".to_string())),
+        };
+        self.playlists[index]
+            .entries
+            .push(PlaylistEntry::from_track(track));
+        self.target = Some(index);
+        self.save(index);
+    }
+
+    /// Start a new playlist with a track and make it the target
+    pub fn add_to_new(&mut self, track: &Track) {
+        let index = self.add(Playlist::new("Playlist".to_string()));
+        self.target = Some(index);
+        self.add_to_target(track);
+    }
+
+    /// Remove the selected entry from the shown playlist
+    pub fn remove_selected_entry(&mut self) {
+        let Some(index) = self.view else { return };
+        let entry = self.selected_entry;
+        let entries = &mut self.playlists[index].entries;
+        if entry >= entries.len() {
+            return;
+        }
+        entries.remove(entry);
+        let len = entries.len();
+        self.selected_entry = entry.min(len.saturating_sub(1));
+
+        // Keep playback on the entry after the playing one
+        if let Some((playing, playing_entry)) = self.playing {
+            if playing == index {
+                self.playing = match len {
+                    0 => None,
+                    _ if playing_entry > entry => Some((playing, playing_entry - 1)),
+                    _ if playing_entry == entry => Some((playing, (entry + len - 1) % len)),
+                    _ => self.playing,
+                };
+            }
+        }
+        self.save(index);
+    }
+
+    /// The entry after the playing one that is in the library, with its index
+    /// in the library. With `wrap` the search goes on from the start of the
+    /// playlist once it reaches the end.
+    pub fn next_entry(&self, library: &MusicLibrary, wrap: bool) -> Option<(usize, usize)> {
+        let (playlist, entry) = self.playing?;
+        let entries = &self.playlists.get(playlist)?.entries;
+        (1..=entries.len())
+            .map(|offset| entry + offset)
+            .take_while(|&next| wrap || next < entries.len())
+            .map(|next| next % entries.len())
+            .find_map(|next| {
+                library
+                    .index_of(&entries[next].path)
+                    .map(|index| (next, index))
+            })
+    }
+
+    /// Make the first entry of the playing playlist with this path the playing
+    /// one, for when the next track was chosen out of playlist order
+    pub fn follow_path(&mut self, path: &Path) {
+        let Some((playlist, _)) = self.playing else {
+            return;
+        };
+        let position = self
+            .playlists
+            .get(playlist)
+            .and_then(|playlist| playlist.entries.iter().position(|entry| entry.path == path));
+        if let Some(entry) = position {
+            self.playing = Some((playlist, entry));
+        }
+    }
+}
+
+fn playlists_dir() -> Option<PathBuf> {
+    get_data_dir().map(|dir| dir.join("playlists"))
+}
+
+/// File of a playlist in the playlists directory
+fn playlist_path(name: &str, extension: &str) -> Option<PathBuf> {
+    playlists_dir().map(|dir| dir.join(format!("{}.{}", file_name(name), extension)))
+}
+
+/// A playlist name with characters that are not safe in file names replaced
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Length as playlists write it: whole seconds, -1 when unknown
+fn length_seconds(duration: Option<Duration>) -> i64 {
+    duration.map_or(-1, |duration| duration.as_secs() as i64)
+}
+
+/// Length in seconds as a duration; negative (unknown), infinite and
+/// out-of-range values give None
+fn parse_length(value: &str) -> Option<Duration> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
+
+/// A playlist location as a path: `file://` URLs are stripped and relative
+/// paths joined to the playlist's directory
+fn resolve_location(location: &str, base: &Path) -> PathBuf {
+    let location = location.strip_prefix("file://").unwrap_or(location);
+    let path = PathBuf::from(location);
+    if path.is_absolute() {
+        path
+    } else {
+        base.join(path)
+    }
+}
+
+fn relative_location(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Parse an extended or plain M3U playlist; `#EXTINF:<seconds>,<title>` lines
+/// describe the location that follows them
+fn parse_m3u(content: &str, base: &Path) -> Vec<PlaylistEntry> {
+    let mut entries = Vec::new();
+    let mut info: Option<(Option<Duration>, Option<String>)> = None;
+
+    for line in content.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // Attributes like tvg-id="..." may follow the length
+            let (length, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            let duration = length.split_whitespace().next().and_then(parse_length);
+            let title = Some(title.trim())
+                .filter(|title| !title.is_empty())
+                .map(str::to_string);
+            info = Some((duration, title));
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let (duration, title) = info.take().unwrap_or_default();
+            entries.push(PlaylistEntry {
+                path: resolve_location(line, base),
+                title,
+                duration,
+            });
+        }
+    }
+    entries
+}
+
+/// Parse a PLS playlist: numbered `FileN`, `TitleN` and `LengthN` keys in
+/// a `[playlist]` section
+fn parse_pls(content: &str, base: &Path) -> Vec<PlaylistEntry> {
+    let mut entries: BTreeMap<u32, PlaylistEntry> = BTreeMap::new();
+
+    for line in content.lines() {
+        let Some((key, value)) = line.trim_start_matches('\u{feff}').split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        let split = key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len());
+        let Ok(number) = key[split..].parse::<u32>() else {
+            continue;
+        };
+
+        let entry = entries.entry(number).or_default();
+        match &key[..split] {
+            "file" => entry.path = resolve_location(value, base),
+            "title" if !value.is_empty() => entry.title = Some(value.to_string()),
+            "length" => entry.duration = parse_length(value),
+            _ => {}
+        }
+    }
+
+    entries
+        .into_values()
+        .filter(|entry| !entry.path.as_os_str().is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_reads_extinf_length_and_title() {
+        let content = "\u{feff}#EXTM3U\n\
+            #EXTINF:123 tvg-id=\"x\",Artist - Title\n\
+            song.mp3\n\
+            # a comment\n\
+            file:///music/other.flac\n";
+        let entries = parse_m3u(content, Path::new("/lists"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, PathBuf::from("/lists/song.mp3"));
+        assert_eq!(entries[0].title.as_deref(), Some("Artist - Title"));
+        assert_eq!(entries[0].duration, Some(Duration::from_secs(123)));
+        assert_eq!(entries[1].path, PathBuf::from("/music/other.flac"));
+        assert_eq!(entries[1].title, None);
+        assert_eq!(entries[1].duration, None);
+    }
+
+    #[test]
+    fn m3u_ignores_unknown_and_invalid_lengths() {
+        let content = "#EXTINF:-1,Stream\na.mp3\n\
+            #EXTINF:inf,Endless\nb.mp3\n\
+            #EXTINF:1e30,Huge\nc.mp3\n\
+            #EXTINF:,Blank\nd.mp3\n";
+        let entries = parse_m3u(content, Path::new("/"));
+        assert_eq!(entries.len(), 4);
+        assert!(entries.iter().all(|entry| entry.duration.is_none()));
+        assert_eq!(entries[1].title.as_deref(), Some("Endless"));
+    }
+
+    #[test]
+    fn pls_collects_numbered_keys() {
+        let content = "[playlist]\n\
+            File2=/music/b.mp3\n\
+            Title2=Second\n\
+            file1=a.mp3\n\
+            LENGTH1=61.5\n\
+            Length2=-1\n\
+            Title3=No file\n\
+            NumberOfEntries=3\n";
+        let entries = parse_pls(content, Path::new("/lists"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, PathBuf::from("/lists/a.mp3"));
+        assert_eq!(entries[0].duration, Some(Duration::from_secs_f64(61.5)));
+        assert_eq!(entries[1].path, PathBuf::from("/music/b.mp3"));
+        assert_eq!(entries[1].title.as_deref(), Some("Second"));
+        assert_eq!(entries[1].duration, None);
+    }
+
+    #[test]
+    fn pls_ignores_infinite_lengths() {
+        let content = "File1=a.mp3\nLength1=inf\nFile2=b.mp3\nLength2=NaN\n";
+        let entries = parse_pls(content, Path::new("/"));
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.duration.is_none()));
+    }
+}
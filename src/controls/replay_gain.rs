@@ -0,0 +1,326 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use symphonia::core::meta::{MetadataRevision, StandardTagKey};
+
+/// Loudness that ReplayGain 2.0 gains are relative to, in LUFS
+pub const REFERENCE_LOUDNESS: f32 = -18.0;
+
+/// Which ReplayGain value the engine applies to a track.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReplayGainMode {
+    /// Every track is normalized on its own
+    #[default]
+    Track,
+    /// Tracks of an album keep their relative levels
+    Album,
+    /// Tracks play at their mastered level
+    Off,
+}
+
+impl ReplayGainMode {
+    pub fn next(self) -> Self {
+        match self {
+            ReplayGainMode::Track => ReplayGainMode::Album,
+            ReplayGainMode::Album => ReplayGainMode::Off,
+            ReplayGainMode::Off => ReplayGainMode::Track,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReplayGainMode::Track => "Track",
+            ReplayGainMode::Album => "Album",
+            ReplayGainMode::Off => "Off",
+        }
+    }
+
+    /// Parse the mode name used in the config file
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "track" => Some(ReplayGainMode::Track),
+            "album" => Some(ReplayGainMode::Album),
+            "off" => Some(ReplayGainMode::Off),
+            _ => None,
+        }
+    }
+}
+
+/// ReplayGain values of a track, either read from its tags or measured by a
+/// loudness scan. Peaks are linear sample amplitudes, 1.0 being full scale.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayGain {
+    pub track_gain_db: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain_db: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Collect the ReplayGain tags of one metadata revision, keeping values that
+    /// were already found in another revision
+    pub fn read_tags(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            let value = tag.value.to_string();
+            let key = match tag.std_key {
+                Some(key) => key,
+                // MP4 files keep ReplayGain in freeform iTunes atoms
+                None => match tag.key.to_ascii_lowercase() {
+                    key if key.ends_with("replaygain_track_gain") => {
+                        StandardTagKey::ReplayGainTrackGain
+                    }
+                    key if key.ends_with("replaygain_track_peak") => {
+                        StandardTagKey::ReplayGainTrackPeak
+                    }
+                    key if key.ends_with("replaygain_album_gain") => {
+                        StandardTagKey::ReplayGainAlbumGain
+                    }
+                    key if key.ends_with("replaygain_album_peak") => {
+                        StandardTagKey::ReplayGainAlbumPeak
+                    }
+                    _ => continue,
+                },
+            };
+
+            let slot = match key {
+                StandardTagKey::ReplayGainTrackGain => &mut self.track_gain_db,
+                StandardTagKey::ReplayGainTrackPeak => &mut self.track_peak,
+                StandardTagKey::ReplayGainAlbumGain => &mut self.album_gain_db,
+                StandardTagKey::ReplayGainAlbumPeak => &mut self.album_peak,
+                _ => continue,
+            };
+            if slot.is_none() {
+                *slot = parse_number(&value);
+            }
+        }
+    }
+
+    pub fn has_track_gain(&self) -> bool {
+        self.track_gain_db.is_some()
+    }
+
+    /// Linear factor to apply for `mode`. Album mode falls back to the track gain
+    /// when there is no album gain. The gain is lowered where needed so the peak
+    /// does not exceed full scale.
+    pub fn factor(&self, mode: ReplayGainMode) -> f32 {
+        let (gain_db, peak) = match mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Album if self.album_gain_db.is_some() => {
+                (self.album_gain_db, self.album_peak)
+            }
+            _ => (self.track_gain_db, self.track_peak),
+        };
+        let Some(gain_db) = gain_db else {
+            return 1.0;
+        };
+
+        let factor = 10f32.powf(gain_db / 20.0);
+        match peak {
+            Some(peak) if peak > 0.0 => factor.min(1.0 / peak),
+            _ => factor,
+        }
+    }
+}
+
+/// Parse values like "-6.48 dB" or "0.988525"
+fn parse_number(value: &str) -> Option<f32> {
+    value
+        .split_whitespace()
+        .next()?
+        .parse::<f32>()
+        .ok()
+        .filter(|number| number.is_finite())
+}
+
+/// Gain factor of one playing track, adjustable from the UI thread
+#[derive(Debug)]
+pub struct GainHandle {
+    factor_bits: AtomicU32,
+}
+
+impl GainHandle {
+    pub fn new(factor: f32) -> Self {
+        Self {
+            factor_bits: AtomicU32::new(factor.to_bits()),
+        }
+    }
+
+    pub fn set(&self, factor: f32) {
+        self.factor_bits.store(factor.to_bits(), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> f32 {
+        f32::from_bits(self.factor_bits.load(Ordering::Relaxed))
+    }
+}
+
+/// A `Source` wrapper applying the ReplayGain factor of a track. Changes of the
+/// factor are spread over a few milliseconds so switching modes does not click.
+pub struct ReplayGainSource<S> {
+    input: S,
+    handle: Arc<GainHandle>,
+    factor: f32,
+    step: f32,
+}
+
+impl<S> ReplayGainSource<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, handle: Arc<GainHandle>) -> Self {
+        let samples_per_ms = input.sample_rate() as f32 * input.channels() as f32 / 1000.0;
+        Self {
+            factor: handle.get(),
+            // Moving the factor by 1.0 takes 20 ms
+            step: 1.0 / (20.0 * samples_per_ms.max(1.0)),
+            input,
+            handle,
+        }
+    }
+}
+
+impl<S> Iterator for ReplayGainSource<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let target = self.handle.get();
+        if self.factor != target {
+            self.factor += (target - self.factor).clamp(-self.step, self.step);
+        }
+        self.input.next().map(|sample| sample * self.factor)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for ReplayGainSource<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symphonia::core::meta::{MetadataBuilder, Tag, Value};
+
+    fn tagged(tags: &[(Option<StandardTagKey>, &str, &str)]) -> ReplayGain {
+        let mut builder = MetadataBuilder::new();
+        for &(std_key, key, value) in tags {
+            builder.add_tag(Tag::new(std_key, key, Value::from(value)));
+        }
+        let mut gain = ReplayGain::default();
+        gain.read_tags(&builder.metadata());
+        gain
+    }
+
+    #[test]
+    fn reads_standard_and_freeform_tags() {
+        let gain = tagged(&[
+            (
+                Some(StandardTagKey::ReplayGainTrackGain),
+                "REPLAYGAIN_TRACK_GAIN",
+                "-6.48 dB",
+            ),
+            (
+                Some(StandardTagKey::ReplayGainTrackPeak),
+                "REPLAYGAIN_TRACK_PEAK",
+                "0.988525",
+            ),
+            (
+                None,
+                "----:com.apple.iTunes:replaygain_album_gain",
+                "-7.1 dB",
+            ),
+        ]);
+        assert_eq!(gain.track_gain_db, Some(-6.48));
+        assert_eq!(gain.track_peak, Some(0.988525));
+        assert_eq!(gain.album_gain_db, Some(-7.1));
+        assert_eq!(gain.album_peak, None);
+    }
+
+    #[test]
+    fn ignores_unreadable_values_and_keeps_the_first_one() {
+        let key = Some(StandardTagKey::ReplayGainTrackGain);
+        let gain = tagged(&[
+            (key, "REPLAYGAIN_TRACK_GAIN", "loud"),
+            (key, "REPLAYGAIN_TRACK_GAIN", "inf dB"),
+            (key, "REPLAYGAIN_TRACK_GAIN", "-3 dB"),
+            (key, "REPLAYGAIN_TRACK_GAIN", "-9 dB"),
+        ]);
+        assert_eq!(gain.track_gain_db, Some(-3.0));
+    }
+
+    #[test]
+    fn factor_follows_the_mode() {
+        let gain = ReplayGain {
+            track_gain_db: Some(-20.0),
+            album_gain_db: Some(-40.0),
+            ..ReplayGain::default()
+        };
+        assert!((gain.factor(ReplayGainMode::Track) - 0.1).abs() < 1e-6);
+        assert!((gain.factor(ReplayGainMode::Album) - 0.01).abs() < 1e-6);
+        assert_eq!(gain.factor(ReplayGainMode::Off), 1.0);
+        assert_eq!(ReplayGain::default().factor(ReplayGainMode::Track), 1.0);
+    }
+
+    #[test]
+    fn album_mode_falls_back_to_the_track_gain() {
+        let gain = ReplayGain {
+            track_gain_db: Some(-20.0),
+            ..ReplayGain::default()
+        };
+        assert!((gain.factor(ReplayGainMode::Album) - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn factor_keeps_the_peak_below_full_scale() {
+        let gain = ReplayGain {
+            track_gain_db: Some(12.0),
+            track_peak: Some(0.5),
+            ..ReplayGain::default()
+        };
+        assert_eq!(gain.factor(ReplayGainMode::Track), 2.0);
+
+        // A peak of zero says nothing about the headroom
+        let silent = ReplayGain {
+            track_peak: Some(0.0),
+            ..gain
+        };
+        assert!(silent.factor(ReplayGainMode::Track) > 3.9);
+    }
+}
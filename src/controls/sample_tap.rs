@@ -0,0 +1,170 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Number of mono samples the ring buffer holds, a power of two
+const TAP_CAPACITY: usize = 1 << 15;
+
+/// Gives every tap its own id so only one of them writes at a time
+static NEXT_TAP_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Lock-free ring buffer of the mono samples that are being played.
+///
+/// The audio thread is the only writer; readers copy the most recent samples and
+/// can tell when the writer has overtaken them.
+#[derive(Debug)]
+pub struct TapBuffer {
+    samples: Box<[AtomicU32]>,
+    /// Total number of samples written so far
+    written: AtomicUsize,
+    /// Id of the tap currently feeding the buffer
+    owner: AtomicUsize,
+    /// Sample rate of the track currently feeding the buffer
+    sample_rate: AtomicU32,
+}
+
+impl Default for TapBuffer {
+    fn default() -> Self {
+        Self {
+            samples: (0..TAP_CAPACITY).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicUsize::new(0),
+            owner: AtomicUsize::new(0),
+            sample_rate: AtomicU32::new(0),
+        }
+    }
+}
+
+impl TapBuffer {
+    pub fn capacity(&self) -> usize {
+        TAP_CAPACITY
+    }
+
+    /// Total number of samples written so far
+    pub fn written(&self) -> usize {
+        self.written.load(Ordering::Acquire)
+    }
+
+    /// Sample rate of the track being played, zero before anything was played
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// Copy the `out.len()` samples that end at sample number `end`. Returns false
+    /// if they have already been overwritten.
+    pub fn read(&self, end: usize, out: &mut [f32]) -> bool {
+        let Some(start) = end.checked_sub(out.len()) else {
+            return false;
+        };
+        for (offset, sample) in out.iter_mut().enumerate() {
+            let bits = self.samples[(start + offset) & (TAP_CAPACITY - 1)].load(Ordering::Relaxed);
+            *sample = f32::from_bits(bits);
+        }
+        // The writer may have wrapped around while we were copying
+        self.written().saturating_sub(start) <= TAP_CAPACITY
+    }
+
+    fn push(&self, sample: f32) {
+        let position = self.written.load(Ordering::Relaxed);
+        self.samples[position & (TAP_CAPACITY - 1)].store(sample.to_bits(), Ordering::Relaxed);
+        self.written.store(position + 1, Ordering::Release);
+    }
+}
+
+/// A `Source` wrapper at the very end of a track's chain that copies the samples,
+/// mixed down to mono, into a shared `TapBuffer` for live analysis.
+///
+/// A tap takes over the buffer when its track starts, so during a crossfade or a
+/// gapless transition the incoming track is the one being analysed.
+pub struct SampleTap<S> {
+    input: S,
+    buffer: Arc<TapBuffer>,
+    id: usize,
+    started: bool,
+    channels: usize,
+    channel: usize,
+    sum: f32,
+}
+
+impl<S> SampleTap<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, buffer: Arc<TapBuffer>) -> Self {
+        Self {
+            channels: input.channels().max(1) as usize,
+            input,
+            buffer,
+            id: NEXT_TAP_ID.fetch_add(1, Ordering::Relaxed),
+            started: false,
+            channel: 0,
+            sum: 0.0,
+        }
+    }
+}
+
+impl<S> Iterator for SampleTap<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        if !self.started {
+            self.started = true;
+            self.buffer.owner.store(self.id, Ordering::Relaxed);
+            self.buffer
+                .sample_rate
+                .store(self.input.sample_rate(), Ordering::Relaxed);
+        }
+
+        self.sum += sample;
+        self.channel += 1;
+        if self.channel == self.channels {
+            if self.buffer.owner.load(Ordering::Relaxed) == self.id {
+                self.buffer.push(self.sum / self.channels as f32);
+            }
+            self.channel = 0;
+            self.sum = 0.0;
+        }
+        Some(sample)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for SampleTap<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::controls::music_library::{MusicLibrary, Track};
+use crate::controls::playback_state::ShuffleMode;
+
+/// One round of shuffled playback: every track of the source once, in random
+/// order, so nothing repeats before everything has played. A new round is
+/// drawn when the source or the mode changes, and when a round is over and
+/// playback repeats.
+#[derive(Debug, Default)]
+pub struct ShuffleRound {
+    /// Tracks still to come in this round, in play order
+    upcoming: Vec<PathBuf>,
+    /// What the round was drawn from: the playing playlist (None for the
+    /// library) and the shuffle mode, None before the first draw
+    drawn_from: Option<(Option<usize>, ShuffleMode)>,
+}
+
+impl ShuffleRound {
+    /// Forget the round so the next one is drawn from scratch
+    pub fn reset(&mut self) {
+        self.upcoming.clear();
+        self.drawn_from = None;
+    }
+
+    /// Whether the round was drawn from this source in this mode
+    pub fn is_drawn_from(&self, source: Option<usize>, mode: ShuffleMode) -> bool {
+        self.drawn_from == Some((source, mode))
+    }
+
+    /// Start a new round over `tracks`. The current track is left out unless
+    /// it is the only one, and in `SpreadArtists` mode the round does not start
+    /// with its artist either.
+    pub fn draw(
+        &mut self,
+        source: Option<usize>,
+        mode: ShuffleMode,
+        tracks: Vec<&Track>,
+        current: Option<&Track>,
+    ) {
+        let mut rng = rand::thread_rng();
+        let current_path = current.map(|track| track.path.as_path());
+        let mut tracks: Vec<&Track> = match tracks
+            .iter()
+            .filter(|track| Some(track.path.as_path()) != current_path)
+            .copied()
+            .collect::<Vec<_>>()
+        {
+            others if others.is_empty() => tracks,
+            others => others,
+        };
+
+        self.upcoming = match mode {
+            ShuffleMode::SpreadArtists => spread_artists(tracks, current, &mut rng),
+            _ => {
+                tracks.shuffle(&mut rng);
+                tracks.iter().map(|track| track.path.clone()).collect()
+            }
+        };
+        self.drawn_from = Some((source, mode));
+    }
+
+    /// Library index of the next track of the round that is still in the
+    /// library, None when the round is over
+    pub fn next(&self, library: &MusicLibrary) -> Option<usize> {
+        self.upcoming.iter().find_map(|path| library.index_of(path))
+    }
+
+    /// Remove the track `next` chose once it starts, along with the tracks
+    /// before it that have left the library
+    pub fn take(&mut self, library: &MusicLibrary, index: usize) -> bool {
+        let Some(path) = library.tracks.get(index).map(|track| track.path.as_path()) else {
+            return false;
+        };
+        match self.upcoming.iter().position(|upcoming| upcoming == path) {
+            Some(position) => {
+                self.upcoming.drain(..=position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Leave a track out of the rest of the round, once it played outside of
+    /// it: picked by hand, from the play queue or from the history
+    pub fn remove(&mut self, path: &Path) {
+        self.upcoming.retain(|upcoming| upcoming != path);
+    }
+}
+
+/// Artist a track is kept apart from others by. Tracks without one count as
+/// an artist of their own.
+fn artist_key(track: &Track) -> String {
+    match track.artist.as_ref().or(track.album_artist.as_ref()) {
+        Some(artist) => artist.to_lowercase(),
+        None => track.path.to_string_lossy().into_owned(),
+    }
+}
+
+/// A random order in which no two neighbouring tracks share an artist, as far
+/// as the mix of artists allows. Artists are picked at random, weighted by how
+/// many of their tracks are left, except that an artist holding more than
+/// half of the rest has to go right away to stay apart.
+fn spread_artists(
+    tracks: Vec<&Track>,
+    current: Option<&Track>,
+    rng: &mut impl Rng,
+) -> Vec<PathBuf> {
+    let mut groups: Vec<(String, Vec<&Path>)> = Vec::new();
+    let mut group_of: HashMap<String, usize> = HashMap::new();
+    for track in &tracks {
+        let key = artist_key(track);
+        let group = *group_of.entry(key.clone()).or_insert_with(|| {
+            groups.push((key, Vec::new()));
+            groups.len() - 1
+        });
+        groups[group].1.push(track.path.as_path());
+    }
+    for (_, paths) in groups.iter_mut() {
+        paths.shuffle(rng);
+    }
+
+    let mut order = Vec::with_capacity(tracks.len());
+    let mut last = current.map(artist_key);
+    let mut remaining = tracks.len();
+    while remaining > 0 {
+        let mut candidates: Vec<usize> = (0..groups.len())
+            .filter(|&i| !groups[i].1.is_empty() && Some(&groups[i].0) != last.as_ref())
+            .collect();
+        if candidates.is_empty() {
+            // Only the last artist is left
+            candidates = (0..groups.len())
+                .filter(|&i| !groups[i].1.is_empty())
+                .collect();
+        }
+
+        let chosen = match candidates
+            .iter()
+            .copied()
+            .find(|&i| groups[i].1.len() * 2 > remaining)
+        {
+            Some(forced) => forced,
+            None => {
+                let mut pick =
+                    rng.gen_range(0..candidates.iter().map(|&i| groups[i].1.len()).sum::<usize>());
+                candidates
+                    .iter()
+                    .copied()
+                    .find(|&i| {
+                        let len = groups[i].1.len();
+                        if pick < len {
+                            true
+                        } else {
+                            pick -= len;
+                            false
+                        }
+                    })
+                    .unwrap_or(candidates[0])
+            }
+        };
+
+        if let Some(path) = groups[chosen].1.pop() {
+            order.push(path.to_path_buf());
+        }
+        last = Some(groups[chosen].0.clone());
+        remaining -= 1;
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn track(artist: &str, number: usize) -> Track {
+        Track {
+            artist: Some(artist.to_string()),
+            path: PathBuf::from(format!("/{}/{}.mp3", artist, number)),
+            ..Track::default()
+        }
+    }
+
+    fn tracks(artists: &[(&str, usize)]) -> Vec<Track> {
+        artists
+            .iter()
+            .flat_map(|&(artist, count)| (0..count).map(move |number| track(artist, number)))
+            .collect()
+    }
+
+    /// Artist of a track from its path, as `track` lays them out
+    fn artist_of(path: &Path) -> String {
+        path.parent().unwrap().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn spread_artists_keeps_neighbours_apart() {
+        let tracks = tracks(&[("a", 4), ("b", 3), ("c", 2)]);
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let order = spread_artists(tracks.iter().collect(), None, &mut rng);
+
+            let mut sorted = order.clone();
+            sorted.sort();
+            let mut expected: Vec<PathBuf> = tracks.iter().map(|t| t.path.clone()).collect();
+            expected.sort();
+            assert_eq!(sorted, expected);
+            assert!(order
+                .windows(2)
+                .all(|pair| artist_of(&pair[0]) != artist_of(&pair[1])));
+        }
+    }
+
+    #[test]
+    fn spread_artists_interleaves_a_dominant_artist() {
+        let tracks = tracks(&[("a", 3), ("b", 2)]);
+        let current = track("b", 9);
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let order = spread_artists(tracks.iter().collect(), Some(&current), &mut rng);
+            let artists: Vec<String> = order.iter().map(|path| artist_of(path)).collect();
+            assert_eq!(artists, ["/a", "/b", "/a", "/b", "/a"]);
+        }
+    }
+
+    #[test]
+    fn spread_artists_does_not_start_with_the_current_artist() {
+        let tracks = tracks(&[("a", 2), ("b", 2)]);
+        let current = track("a", 9);
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let order = spread_artists(tracks.iter().collect(), Some(&current), &mut rng);
+            assert_eq!(artist_of(&order[0]), "/b");
+        }
+    }
+}
@@ -1,35 +1,45 @@
+use crate::controls::replay_gain::ReplayGainMode;
 use crate::log_debug;
 
 /// Audio settings with well-defined constraints for controlling sound characteristics.
 ///
 /// This struct manages all user-adjustable audio parameters:
 /// - Volume: Controls overall playback loudness (0-100)
-/// - Bass: Enhances or reduces low frequencies (0-100)
-/// - Treble: Enhances or reduces high frequencies (0-100)
-/// - Pitch: Controls audio pitch adjustment (-100-100)
+/// - Bass: Enhances or reduces low frequencies (0-100, 50 is flat)
+/// - Treble: Enhances or reduces high frequencies (0-100, 50 is flat)
+/// - Pitch: Transposes the audio in semitones without changing tempo (-12-12)
+/// - Tempo: Playback tempo in percent without changing pitch (50-200)
+/// - ReplayGain mode: Which loudness normalization applies (track, album, off)
 #[derive(Debug, Clone)]
 pub struct SoundControl {
     volume: f32,
     bass: f32,
     treble: f32,
     pitch: f32,
+    tempo: f32,
+    replay_gain_mode: ReplayGainMode,
 }
 
 impl Default for SoundControl {
     fn default() -> Self {
         Self {
             volume: 50.0,
-            bass: 0.0,
-            treble: 0.0,
+            bass: 50.0,
+            treble: 50.0,
             pitch: 0.0,
+            tempo: 100.0,
+            replay_gain_mode: ReplayGainMode::default(),
         }
     }
 }
 
 impl SoundControl {
     /// Creates a new SoundControl with validated initial values
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(replay_gain_mode: ReplayGainMode) -> Self {
+        Self {
+            replay_gain_mode,
+            ..Self::default()
+        }
     }
 
     /// Adjusts the volume by a delta and clamps it within the valid range
@@ -52,9 +62,22 @@ impl SoundControl {
 
     /// Adjusts the pitch by a delta and clamps it within the valid range
     pub fn adjust_pitch(&mut self, delta: f32) {
-        self.pitch = (self.pitch + delta).clamp(-100.0, 100.0);
+        self.pitch = (self.pitch + delta).clamp(-12.0, 12.0);
         log_debug!("Pitch adjusted to {}", self.pitch);
     }
+
+    /// Adjusts the tempo by a delta and clamps it within the valid range
+    pub fn adjust_tempo(&mut self, delta: f32) {
+        self.tempo = (self.tempo + delta).clamp(50.0, 200.0);
+        log_debug!("Tempo adjusted to {}", self.tempo);
+    }
+
+    /// Switches to the next ReplayGain mode
+    pub fn cycle_replay_gain_mode(&mut self) {
+        self.replay_gain_mode = self.replay_gain_mode.next();
+        log_debug!("ReplayGain mode set to {}", self.replay_gain_mode.label());
+    }
+
     /// Getter for volume
     pub fn volume(&self) -> f32 {
         self.volume
@@ -73,4 +96,14 @@ impl SoundControl {
     pub fn pitch(&self) -> f32 {
         self.pitch
     }
+
+    /// Getter for tempo
+    pub fn tempo(&self) -> f32 {
+        self.tempo
+    }
+
+    /// Getter for the ReplayGain mode
+    pub fn replay_gain_mode(&self) -> ReplayGainMode {
+        self.replay_gain_mode
+    }
 }
@@ -1,9 +1,173 @@
-use std::{error::Error, fs::File, io::BufReader, path::Path, sync::mpsc};
+use std::{
+    error::Error,
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::{mpsc, Arc},
+};
 
 use rodio::{Decoder, Source};
-use rustfft::{num_complex::Complex, FftDirection, FftPlanner};
+use rustfft::{num_complex::Complex, Fft, FftDirection, FftPlanner};
+use serde::Deserialize;
 
-use crate::log_debug;
+use crate::controls::sample_tap::TapBuffer;
+use crate::log_error;
+
+/// Where the visualizer gets its spectrum from
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpectrumSource {
+    /// Analyse the samples that are being played
+    #[default]
+    Live,
+    /// Analyse the whole file ahead of time and follow the track position
+    Offline,
+}
+
+/// Window function applied to each block before the FFT
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowFunction {
+    Rectangular,
+    Hann,
+    #[default]
+    Hamming,
+    Blackman,
+    Nuttall,
+}
+
+impl WindowFunction {
+    fn coefficients(self, size: usize) -> Vec<f32> {
+        let window: Vec<f64> = match self {
+            WindowFunction::Rectangular => vec![1.0; size],
+            WindowFunction::Hann => apodize::hanning_iter(size).collect(),
+            WindowFunction::Hamming => apodize::hamming_iter(size).collect(),
+            WindowFunction::Blackman => apodize::blackman_iter(size).collect(),
+            WindowFunction::Nuttall => apodize::nuttall_iter(size).collect(),
+        };
+        window.into_iter().map(|w| w as f32).collect()
+    }
+}
+
+/// Spectrum analysis settings, read from the `spectrum` section of the config file
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct SpectrumSettings {
+    pub source: SpectrumSource,
+    /// Number of samples per FFT
+    pub window_size: usize,
+    pub window: WindowFunction,
+    /// Fraction by which consecutive FFT blocks overlap, 0 to 0.95
+    pub overlap: f32,
+}
+
+impl Default for SpectrumSettings {
+    fn default() -> Self {
+        Self {
+            source: SpectrumSource::Live,
+            window_size: 1024,
+            window: WindowFunction::Hamming,
+            overlap: 0.5,
+        }
+    }
+}
+
+/// FFT magnitudes of one frame and the sample rate of the audio they describe
+#[derive(Debug, Clone, Default)]
+pub struct SpectrumFrame {
+    pub magnitudes: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+/// Most FFT blocks averaged into one frame, older pending blocks are skipped
+const MAX_BLOCKS_PER_FRAME: usize = 8;
+
+/// Spectrum of the audio that is being played, computed from a `TapBuffer`.
+///
+/// Every call to `update` analyses the blocks that became available since the
+/// previous call, `hop` samples apart, and averages them into the current frame.
+pub struct LiveSpectrum {
+    tap: Arc<TapBuffer>,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    hop: usize,
+    /// Sample number where the last analysed block ended
+    analyzed: usize,
+    block: Vec<f32>,
+    buffer: Vec<Complex<f32>>,
+    frame: Vec<f32>,
+}
+
+impl LiveSpectrum {
+    pub fn new(tap: Arc<TapBuffer>, settings: &SpectrumSettings) -> Self {
+        let size = settings.window_size.clamp(64, tap.capacity() / 4);
+        let overlap = settings.overlap.clamp(0.0, 0.95);
+        let hop = ((size as f32 * (1.0 - overlap)) as usize).max(1);
+
+        Self {
+            fft: FftPlanner::<f32>::new().plan_fft(size, FftDirection::Forward),
+            window: settings.window.coefficients(size),
+            hop,
+            analyzed: tap.written(),
+            tap,
+            block: vec![0.0; size],
+            buffer: vec![Complex::default(); size],
+            frame: Vec::new(),
+        }
+    }
+
+    /// Analyse the samples played since the last call
+    pub fn update(&mut self) {
+        let written = self.tap.written();
+        if written < self.analyzed + self.hop {
+            return;
+        }
+
+        let pending = (written - self.analyzed) / self.hop;
+        let skipped = pending.saturating_sub(MAX_BLOCKS_PER_FRAME);
+        let mut end = self.analyzed + (skipped + 1) * self.hop;
+        self.analyzed += pending * self.hop;
+
+        let mut frame = vec![0.0; self.block.len()];
+        let mut blocks = 0;
+        while end <= self.analyzed {
+            if self.tap.read(end, &mut self.block) {
+                for ((value, sample), weight) in
+                    self.buffer.iter_mut().zip(&self.block).zip(&self.window)
+                {
+                    *value = Complex {
+                        re: sample * weight,
+                        im: 0.0,
+                    };
+                }
+                self.fft.process(&mut self.buffer);
+                for (magnitude, value) in frame.iter_mut().zip(&self.buffer) {
+                    *magnitude += value.norm();
+                }
+                blocks += 1;
+            }
+            end += self.hop;
+        }
+
+        if blocks > 0 {
+            frame.iter_mut().for_each(|m| *m /= blocks as f32);
+            self.frame = frame;
+        }
+    }
+
+    /// Magnitudes of the latest frame, empty until something was played
+    pub fn frame(&self) -> SpectrumFrame {
+        SpectrumFrame {
+            magnitudes: self.frame.clone(),
+            sample_rate: self.tap.sample_rate(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frame.clear();
+        self.analyzed = self.tap.written();
+    }
+}
 
 /// Stores frequency spectrum data for audio visualization.
 ///
@@ -15,89 +179,121 @@ pub struct Spectrum {
     pub inner: Vec<f32>,
     pub size: usize,
     pub fps: usize,
+    pub sample_rate: u32,
     pub processing: bool,
-    receiver: Option<mpsc::Receiver<(Vec<f32>, usize)>>,
+    /// Why the analysis failed, if it did
+    pub error: Option<String>,
+    receiver: Option<mpsc::Receiver<AnalysisResult>>,
 }
 
+/// Magnitudes of all frames, the frame size and the sample rate, or why the
+/// analysis failed
+type AnalysisResult = Result<(Vec<f32>, usize, u32), String>;
+
+/// Frames per second the offline analysis produces
+const OFFLINE_FPS: usize = 60;
+
 impl Spectrum {
     pub fn fft_async(path: impl AsRef<Path>) -> Spectrum {
         let (tx, rx) = mpsc::channel();
 
         let path = path.as_ref().to_path_buf();
-        std::thread::spawn(move || match Self::calculate_fft(&path) {
-            Ok((inner, size)) => {
-                let _ = tx.send((inner, size));
-            }
-            Err(e) => {
-                log_debug!("tx send error: {:?}", e);
-                let _ = tx.send((Vec::new(), 0));
+        std::thread::spawn(move || {
+            let result = Self::calculate_fft(&path).map_err(|e| e.to_string());
+            if let Err(e) = &result {
+                log_error!("Spectrum analysis of {:?} failed: {}", path, e);
             }
+            let _ = tx.send(result);
         });
 
         Spectrum {
             inner: Vec::new(),
             size: 0,
-            fps: 60,
-            processing: false,
+            fps: OFFLINE_FPS,
+            sample_rate: 0,
+            processing: true,
+            error: None,
             receiver: Some(rx),
         }
     }
 
-    fn calculate_fft(path: &Path) -> Result<(Vec<f32>, usize), Box<dyn Error>> {
+    /// Decode a file of any supported format and compute one FFT frame per
+    /// 1/60 s of audio. The frame hop is fractional, so the frames stay aligned
+    /// with the track position at any sample rate.
+    fn calculate_fft(path: &Path) -> Result<(Vec<f32>, usize, u32), Box<dyn Error>> {
         let src = File::open(path)?;
-        let source = Decoder::new_mp3(BufReader::new(src))?;
+        let source = Decoder::new(BufReader::new(src))?;
         let samples = source.convert_samples::<f32>();
 
-        let ch = samples.channels() as usize;
+        let channels = samples.channels().max(1) as usize;
         let rate = samples.sample_rate();
-        assert!(rate % 60 == 0);
-        let size = (rate / 60) as usize;
-        let msize = size * ch;
+        if rate == 0 {
+            return Err("Invalid sample rate".into());
+        }
+        let hop = rate as f64 / OFFLINE_FPS as f64;
+        let size = (hop.round() as usize).max(2);
 
         let mut planner = FftPlanner::<f32>::new();
         let fft = planner.plan_fft(size, FftDirection::Forward);
+        let window = WindowFunction::Hamming.coefficients(size);
 
-        let mut slices = vec![];
-
-        let hamming = apodize::hamming_iter(size)
-            .map(|n| n as f32)
-            .collect::<Vec<f32>>();
+        let mut out = Vec::new();
+        let mut buffer = vec![Complex::default(); size];
+        // Mono samples from absolute position `offset` on
+        let mut mono: Vec<f32> = Vec::with_capacity(size * 2);
+        let mut offset = 0;
+        let mut frame_index = 0;
+        let mut sum = 0.0;
 
-        let samples = samples.buffered();
+        for (k, sample) in samples.enumerate() {
+            sum += sample;
+            if k % channels != channels - 1 {
+                continue;
+            }
+            mono.push(sum / channels as f32);
+            sum = 0.0;
 
-        let mut buffer = vec![];
-        for (k, b) in samples.enumerate() {
-            if k != 0 && k % msize == 0 {
-                fft.process(&mut buffer);
-                if buffer.len() != size {
-                    break;
-                }
-                slices.append(&mut buffer);
+            let start = (frame_index as f64 * hop).round() as usize;
+            if start + size > offset + mono.len() {
+                continue;
             }
-            if k % ch == 0 {
-                buffer.push(Complex {
-                    re: b * hamming[(k % msize) / 2] as f32,
+
+            let block = &mono[start - offset..start - offset + size];
+            for ((value, sample), weight) in buffer.iter_mut().zip(block).zip(&window) {
+                *value = Complex {
+                    re: sample * weight,
                     im: 0.0,
-                });
+                };
             }
-        }
+            fft.process(&mut buffer);
+            out.extend(buffer.iter().map(|v| v.norm()));
 
-        let out = slices
-            .into_iter()
-            .map(|v| (v.re * v.re + v.im * v.im).sqrt())
-            .collect::<Vec<f32>>();
+            frame_index += 1;
+            let next_start = (frame_index as f64 * hop).round() as usize;
+            mono.drain(..(next_start - offset).min(mono.len()));
+            offset = next_start;
+        }
 
-        Ok((out, size))
+        if out.is_empty() {
+            return Err("Track is too short to analyse".into());
+        }
+        Ok((out, size, rate))
     }
 
     pub fn update(&mut self) {
         if let Some(ref receiver) = self.receiver {
-            if let Ok((inner, size)) = receiver.try_recv() {
-                if !inner.is_empty() {
+            match receiver.try_recv() {
+                Ok(Ok((inner, size, sample_rate))) => {
                     self.inner = inner;
                     self.size = size;
+                    self.sample_rate = sample_rate;
+                    self.processing = false;
+                }
+                Ok(Err(e)) => {
+                    self.error = Some(e);
                     self.processing = false;
                 }
+                Err(_) => {}
             }
         }
     }
@@ -107,59 +303,16 @@ impl Spectrum {
     #[allow(unused)]
     #[deprecated = "Synchronous method stops takes long time for large file; use fft_async instead"]
     pub fn fft(path: impl AsRef<Path>) -> Result<Spectrum, Box<dyn Error>> {
-        let src = File::open(path).unwrap();
-
-        let source = Decoder::new_mp3(BufReader::new(src)).unwrap();
-
-        let samples = source.convert_samples::<f32>();
-
-        let ch = samples.channels() as usize;
-        let rate = samples.sample_rate();
-        assert!(rate % 60 == 0); // assume sample rate is divisible by 60 so that we can stream each frame 60 times per second
-        let size = (rate / 60) as usize;
-        let msize = size * ch;
-
-        let mut planner = FftPlanner::<f32>::new();
-        let fft = planner.plan_fft(size, FftDirection::Forward);
-
-        let mut slices = vec![];
-
-        let hamming = apodize::hamming_iter(size)
-            .map(|n| n as f32)
-            .collect::<Vec<f32>>();
-
-        let samples = samples.buffered();
-
-        // TODO buffer not needed bc.: process(&mut slices[a..b])
-        let mut buffer = vec![];
-        for (k, b) in samples.enumerate() {
-            if k != 0 && k % msize == 0 {
-                fft.process(&mut buffer);
-                if buffer.len() != size {
-                    break;
-                }
-                slices.append(&mut buffer);
-            }
-            if k % ch == 0 {
-                buffer.push(Complex {
-                    re: b * hamming[(k % msize) / 2] as f32,
-                    im: 0.0,
-                });
-            }
-        }
-
-        let out = slices
-            .into_iter()
-            .map(|v| (v.re * v.re + v.im * v.im).sqrt())
-            .collect::<Vec<f32>>();
+        let (inner, size, sample_rate) = Self::calculate_fft(path.as_ref())?;
 
         // in buffer, frames are every `size`, 60 frames = 1sec
-
         Ok(Spectrum {
-            inner: out,
+            inner,
             size,
-            fps: 60,
+            fps: OFFLINE_FPS,
+            sample_rate,
             processing: false,
+            error: None,
             receiver: None,
         })
     }
@@ -0,0 +1,397 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Length of one WSOLA analysis window in seconds
+const WINDOW_SECONDS: f32 = 0.04;
+/// How far (in seconds) a window may be moved to find the best matching waveform
+const TOLERANCE_SECONDS: f32 = 0.01;
+/// Only every n-th sample takes part in the similarity measure, keeps the search cheap
+const CORRELATION_STRIDE: usize = 4;
+/// Candidate offsets are tried in steps of this many frames
+const SEARCH_STEP: usize = 2;
+
+/// Pitch and tempo targets, shared between the UI thread and the audio thread.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeStretchSettings {
+    /// Pitch shift in semitones, 0 keeps the original key
+    pub pitch_semitones: f32,
+    /// Playback tempo as a factor, 1.0 keeps the original tempo
+    pub tempo: f32,
+}
+
+impl Default for TimeStretchSettings {
+    fn default() -> Self {
+        Self {
+            pitch_semitones: 0.0,
+            tempo: 1.0,
+        }
+    }
+}
+
+impl TimeStretchSettings {
+    fn pitch_ratio(&self) -> f32 {
+        2f32.powf(self.pitch_semitones / 12.0)
+    }
+}
+
+pub type SharedTimeStretch = Arc<Mutex<TimeStretchSettings>>;
+
+/// A `Source` wrapper that changes tempo and pitch independently.
+///
+/// The stream is first time-stretched with WSOLA (waveform similarity overlap-add)
+/// by `pitch / tempo`, then resampled by `pitch`. The resampling raises or lowers
+/// the key and undoes the length change the key change would cause, so what is left
+/// is the tempo change alone. With both controls neutral the overlap-add
+/// reconstructs the input exactly.
+pub struct TimeStretch<S> {
+    input: S,
+    settings: SharedTimeStretch,
+    pitch_ratio: f64,
+    stretch: f64,
+    channels: usize,
+    sample_rate: u32,
+    /// Frame position in `input_buffer` where the input ended, if it has
+    input_end: Option<usize>,
+    /// Number of stretched frames the input read so far amounts to
+    expected_output: f64,
+    /// Number of frames the WSOLA stage has produced since the last reset
+    emitted: usize,
+
+    // WSOLA stage
+    window: Vec<f32>,
+    window_len: usize,
+    hop: usize,
+    tolerance: usize,
+    /// Interleaved input not yet consumed by the analysis
+    input_buffer: Vec<f32>,
+    /// Nominal frame position of the next analysis window in `input_buffer`
+    analysis_pos: f64,
+    /// Frame position that would continue the previous window seamlessly
+    natural_pos: Option<usize>,
+    /// Interleaved overlap-add accumulator, one window long
+    overlap: Vec<f32>,
+    /// Interleaved output of the WSOLA stage waiting to be resampled
+    stretched: VecDeque<f32>,
+
+    // Resampling stage
+    resample_pos: f64,
+    frame: Vec<f32>,
+    frame_index: usize,
+}
+
+impl<S> TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, settings: SharedTimeStretch) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let sample_rate = input.sample_rate();
+        let window_len = ((sample_rate as f32 * WINDOW_SECONDS) as usize / 2 * 2).max(64);
+        let tolerance =
+            (sample_rate as f32 * TOLERANCE_SECONDS) as usize / SEARCH_STEP * SEARCH_STEP;
+
+        // Periodic Hann window: two copies at half a window apart sum to exactly one
+        let window = (0..window_len)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / window_len as f32).cos())
+            .collect();
+
+        let mut stretch = Self {
+            input,
+            settings,
+            pitch_ratio: 1.0,
+            stretch: 1.0,
+            channels,
+            sample_rate,
+            input_end: None,
+            expected_output: 0.0,
+            emitted: 0,
+            window,
+            window_len,
+            hop: window_len / 2,
+            tolerance,
+            input_buffer: Vec::new(),
+            analysis_pos: 0.0,
+            natural_pos: None,
+            overlap: vec![0.0; window_len * channels],
+            stretched: VecDeque::new(),
+            resample_pos: 0.0,
+            frame: vec![0.0; channels],
+            frame_index: channels,
+        };
+        stretch.refresh();
+        stretch.reset();
+        stretch
+    }
+
+    fn refresh(&mut self) {
+        if let Some(settings) = self.settings.try_lock() {
+            let pitch_ratio = settings.pitch_ratio() as f64;
+            self.pitch_ratio = pitch_ratio;
+            self.stretch = pitch_ratio / settings.tempo.max(0.01) as f64;
+        }
+    }
+
+    /// Drop all buffered audio, e.g. after a seek
+    fn reset(&mut self) {
+        // Half a window of silence in front lets the first real frame be covered by
+        // two overlapping windows; the silent part is skipped by the resampler.
+        // Windows copy their input 1:1, so the silence takes up exactly `hop`
+        // stretched frames whatever the stretch.
+        self.input_buffer.clear();
+        self.input_buffer.resize(self.hop * self.channels, 0.0);
+        self.analysis_pos = 0.0;
+        self.natural_pos = None;
+        self.overlap.iter_mut().for_each(|s| *s = 0.0);
+        self.stretched.clear();
+        self.resample_pos = self.hop as f64;
+        self.frame_index = self.channels;
+        self.input_end = None;
+        self.expected_output = self.hop as f64;
+        self.emitted = 0;
+    }
+
+    fn buffered_frames(&self) -> usize {
+        self.input_buffer.len() / self.channels
+    }
+
+    /// Read input until `frames` frames are buffered or the input ends
+    fn fill_input(&mut self, frames: usize) {
+        if self.input_end.is_some() {
+            return;
+        }
+        while self.buffered_frames() < frames {
+            match self.input.next() {
+                Some(sample) => {
+                    self.input_buffer.push(sample);
+                    self.expected_output += self.stretch / self.channels as f64;
+                }
+                None => {
+                    // Keep whole frames only
+                    let complete = self.buffered_frames() * self.channels;
+                    self.input_buffer.truncate(complete);
+                    self.input_end = Some(self.buffered_frames());
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Sum of all channels of one frame, used for the similarity search
+    fn mono(&self, frame: usize) -> f32 {
+        let start = frame * self.channels;
+        self.input_buffer[start..start + self.channels].iter().sum()
+    }
+
+    /// Find the window start near `nominal` whose beginning best matches the
+    /// natural continuation of the previous window
+    fn best_offset(&self, nominal: usize, natural: usize) -> usize {
+        let first = nominal.saturating_sub(self.tolerance);
+        let last = nominal + self.tolerance;
+
+        let mut best = nominal;
+        let mut best_score = f32::MIN;
+        for candidate in (first..=last).step_by(SEARCH_STEP) {
+            let mut correlation = 0.0;
+            let mut energy = 0.0;
+            for i in (0..self.hop).step_by(CORRELATION_STRIDE) {
+                let sample = self.mono(candidate + i);
+                correlation += sample * self.mono(natural + i);
+                energy += sample * sample;
+            }
+            let score = correlation / (energy.sqrt() + 1e-9);
+            if score > best_score {
+                best_score = score;
+                best = candidate;
+            }
+        }
+        best
+    }
+
+    /// Overlap-add one more window, producing half a window of stretched audio.
+    /// Returns false once the input is exhausted.
+    fn wsola_step(&mut self) -> bool {
+        self.refresh();
+
+        let nominal = self.analysis_pos.round() as usize;
+        let natural = self.natural_pos.unwrap_or(nominal);
+        let needed = (nominal + self.tolerance).max(natural) + self.window_len;
+        self.fill_input(needed);
+
+        // Once the input has ended, stop exactly where the stretched input does, so a
+        // track appended behind this one follows without any padding in between
+        let remaining = if self.input_end.is_some() {
+            (self.expected_output.round() as usize).saturating_sub(self.emitted)
+        } else {
+            self.hop
+        };
+        if remaining == 0 || self.input_end.is_some_and(|end| nominal >= end) {
+            return false;
+        }
+        // Pad the tail with silence so the last frames are flushed too
+        if self.buffered_frames() < needed {
+            self.input_buffer.resize(needed * self.channels, 0.0);
+        }
+
+        let segment = if self.natural_pos.is_none() || (self.stretch - 1.0).abs() < 1e-6 {
+            // Without stretching the natural continuation is the perfect match
+            self.analysis_pos = natural as f64;
+            natural
+        } else {
+            self.best_offset(nominal, natural)
+        };
+
+        let channels = self.channels;
+        for i in 0..self.window_len {
+            let weight = self.window[i];
+            let source = (segment + i) * channels;
+            let target = i * channels;
+            for c in 0..channels {
+                self.overlap[target + c] += weight * self.input_buffer[source + c];
+            }
+        }
+
+        // The first half of the accumulator has received both of its windows
+        let done = self.hop * channels;
+        let frames = remaining.min(self.hop);
+        self.stretched
+            .extend(self.overlap.drain(..done).take(frames * channels));
+        self.emitted += frames;
+        self.overlap.resize(self.window_len * channels, 0.0);
+
+        self.natural_pos = Some(segment + self.hop);
+        self.analysis_pos += self.hop as f64 / self.stretch;
+
+        // Forget input that no future window can reach any more
+        let keep_from = (self.analysis_pos as usize)
+            .saturating_sub(self.tolerance)
+            .min(segment + self.hop);
+        if keep_from > self.window_len * 4 {
+            self.input_buffer.drain(..keep_from * channels);
+            self.analysis_pos -= keep_from as f64;
+            self.input_end = self.input_end.map(|end| end.saturating_sub(keep_from));
+            self.natural_pos = Some(segment + self.hop - keep_from);
+        }
+
+        true
+    }
+
+    /// Produce the next output frame into `self.frame`
+    fn next_frame(&mut self) -> bool {
+        let channels = self.channels;
+        let index = self.resample_pos as usize;
+
+        while self.stretched.len() / channels < index + 2 {
+            if !self.wsola_step() {
+                break;
+            }
+        }
+
+        let available = self.stretched.len() / channels;
+        if index >= available {
+            return false;
+        }
+
+        let fraction = (self.resample_pos - index as f64) as f32;
+        for c in 0..channels {
+            let current = self.stretched[index * channels + c];
+            let next = if index + 1 < available {
+                self.stretched[(index + 1) * channels + c]
+            } else {
+                current
+            };
+            self.frame[c] = current + (next - current) * fraction;
+        }
+
+        self.resample_pos += self.pitch_ratio;
+        let consumed = (self.resample_pos as usize).min(available);
+        self.stretched.drain(..consumed * channels);
+        self.resample_pos -= consumed as f64;
+
+        true
+    }
+}
+
+impl<S> Iterator for TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_index == self.channels {
+            if !self.next_frame() {
+                return None;
+            }
+            self.frame_index = 0;
+        }
+
+        let sample = self.frame[self.frame_index];
+        self.frame_index += 1;
+        Some(sample)
+    }
+}
+
+impl<S> Source for TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.reset();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn stretch(samples: &[f32], settings: TimeStretchSettings) -> Vec<f32> {
+        let input = SamplesBuffer::new(2, 44100, samples.to_vec());
+        TimeStretch::new(input, Arc::new(Mutex::new(settings))).collect()
+    }
+
+    fn test_signal(frames: usize) -> Vec<f32> {
+        (0..frames * 2)
+            .map(|i| ((i / 2) as f32 * 0.05).sin() * if i % 2 == 0 { 0.8 } else { -0.5 })
+            .collect()
+    }
+
+    #[test]
+    fn unity_settings_reproduce_the_input() {
+        let input = test_signal(10_000);
+        let output = stretch(&input, TimeStretchSettings::default());
+        assert_eq!(output.len(), input.len());
+        for (out, expected) in output.iter().zip(&input) {
+            assert!((out - expected).abs() < 1e-5, "{} != {}", out, expected);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+use super::time_stretch::SharedTimeStretch;
+
+/// Number of samples between two updates of the shared position
+const PUBLISH_INTERVAL: u64 = 512;
+
+const PENDING: u8 = 0;
+const STARTED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+/// Playback progress of one track, written by the audio thread and read by the UI.
+///
+/// The position is taken where samples leave the processing chain, so audio still
+/// buffered in the time stretch does not count yet, and is given in the track's own
+/// timeline whatever the tempo. A track that was appended behind the playing one
+/// also reports when its first sample has left the chain, which is the moment
+/// playback crossed over to it.
+#[derive(Debug, Default)]
+pub struct TrackProgress {
+    position_micros: AtomicU64,
+    state: AtomicU8,
+}
+
+impl TrackProgress {
+    /// Whether the audio thread has started pulling samples of this track
+    pub fn has_started(&self) -> bool {
+        self.state.load(Ordering::Acquire) == STARTED
+    }
+
+    /// Make the track end before its first sample. Returns false if it is too late
+    /// because the track already started.
+    pub fn cancel(&self) -> bool {
+        self.state
+            .compare_exchange(PENDING, CANCELLED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    /// Mark the track as started unless it was cancelled before
+    fn start(&self) -> bool {
+        match self
+            .state
+            .compare_exchange(PENDING, STARTED, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => true,
+            Err(state) => state == STARTED,
+        }
+    }
+
+    pub fn position(&self) -> Duration {
+        Duration::from_micros(self.position_micros.load(Ordering::Relaxed))
+    }
+
+    fn set_position(&self, position: Duration) {
+        self.position_micros
+            .store(position.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// A `Source` wrapper placed at the end of the processing chain that reports how
+/// far into the track playback has progressed. Every output sample stands for
+/// `tempo` samples of the track.
+pub struct TrackedSource<S> {
+    input: S,
+    progress: Arc<TrackProgress>,
+    time_stretch: SharedTimeStretch,
+    tempo: f64,
+    offset: Duration,
+    /// Seconds of the track played since `offset`
+    elapsed: f64,
+    until_publish: u64,
+    started: bool,
+}
+
+impl<S> TrackedSource<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, progress: Arc<TrackProgress>, time_stretch: SharedTimeStretch) -> Self {
+        progress.set_position(Duration::ZERO);
+        let tempo = time_stretch.lock().tempo.max(0.01) as f64;
+        Self {
+            input,
+            progress,
+            time_stretch,
+            tempo,
+            offset: Duration::ZERO,
+            elapsed: 0.0,
+            until_publish: PUBLISH_INTERVAL,
+            started: false,
+        }
+    }
+
+    /// Count the last `PUBLISH_INTERVAL` samples at the current tempo
+    fn publish(&mut self) {
+        // Never wait for the UI thread here; the last tempo will do
+        if let Some(settings) = self.time_stretch.try_lock() {
+            self.tempo = settings.tempo.max(0.01) as f64;
+        }
+        let rate = self.input.sample_rate() as f64 * self.input.channels().max(1) as f64;
+        self.elapsed += PUBLISH_INTERVAL as f64 * self.tempo / rate;
+        self.progress
+            .set_position(self.offset + Duration::from_secs_f64(self.elapsed));
+    }
+}
+
+impl<S> Iterator for TrackedSource<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if !self.started {
+            if !self.progress.start() {
+                return None;
+            }
+            self.started = true;
+        }
+        let sample = self.input.next()?;
+        self.until_publish -= 1;
+        if self.until_publish == 0 {
+            self.until_publish = PUBLISH_INTERVAL;
+            self.publish();
+        }
+        Some(sample)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for TrackedSource<S>
+where
+    S: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.offset = pos;
+        self.elapsed = 0.0;
+        self.until_publish = PUBLISH_INTERVAL;
+        self.progress.set_position(pos);
+        Ok(())
+    }
+}
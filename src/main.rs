@@ -1,4 +1,4 @@
-use config::get_music_dir;
+use config::get_library_roots;
 
 use std::{
     io,
@@ -56,15 +56,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     log_debug!("Terminal UI initialized");
 
-    // Get music directory and create app
-    let dir = get_music_dir();
-    let music_dir = {
-        log_debug!("Using music directory: {:?}", dir);
-        dir
-    };
+    // Get music directories and create app
+    let roots = get_library_roots();
+    log_debug!("Using music directories: {:?}", roots.dirs);
 
     // Create the app, handling any errors
-    let app_result = App::new(music_dir);
+    let app_result = App::new(roots);
     let mut app = match app_result {
         Ok(app) => {
             log_debug!("Application initialized successfully");
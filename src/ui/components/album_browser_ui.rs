@@ -0,0 +1,278 @@
+use parking_lot::Mutex;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+use std::sync::Arc;
+
+use crate::controls::album_browser::AlbumBrowser;
+use crate::controls::list_viewport::ListViewport;
+use crate::controls::music_library::MusicLibrary;
+use crate::ui::components::music_library_ui::render_scrolled;
+
+/// Album artists, their albums and the albums' tracks as three columns, in the
+/// library pane
+pub struct AlbumBrowserUI {
+    style: AlbumBrowserStyle,
+}
+
+struct AlbumBrowserStyle {
+    title_color: Color,
+    artist_color: Color,
+    album_color: Color,
+    track_color: Color,
+    detail_color: Color,
+    playing_color: Color,
+    selected_bg_color: Color,
+    selected_fg_color: Color,
+    trail_bg_color: Color,
+    stats_bg_color: Color,
+    stats_fg_color: Color,
+}
+
+impl Default for AlbumBrowserStyle {
+    fn default() -> Self {
+        Self {
+            title_color: Color::Cyan,
+            artist_color: Color::LightBlue,
+            album_color: Color::LightMagenta,
+            track_color: Color::White,
+            detail_color: Color::DarkGray,
+            playing_color: Color::DarkGray,
+            selected_bg_color: Color::LightGreen,
+            selected_fg_color: Color::Black,
+            trail_bg_color: Color::DarkGray,
+            stats_bg_color: Color::Cyan,
+            stats_fg_color: Color::Black,
+        }
+    }
+}
+
+impl AlbumBrowserUI {
+    pub fn new() -> Self {
+        Self {
+            style: AlbumBrowserStyle::default(),
+        }
+    }
+
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        lib_state: Arc<Mutex<MusicLibrary>>,
+        browser: Arc<Mutex<AlbumBrowser>>,
+        viewport: Arc<Mutex<ListViewport>>,
+    ) {
+        let lib_state = lib_state.lock();
+        let browser = browser.lock();
+        let mut viewport = viewport.lock();
+
+        let block = Block::default()
+            .title("Music Library: Albums")
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(self.style.title_color));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Ratio(1, 4),
+                Constraint::Ratio(1, 4),
+                Constraint::Ratio(1, 2),
+            ])
+            .split(inner);
+
+        let artists: Vec<Line> = browser
+            .artists
+            .iter()
+            .map(|artist| {
+                Line::from(vec![
+                    Span::styled(
+                        artist.name.clone(),
+                        Style::default().fg(self.style.artist_color),
+                    ),
+                    Span::styled(
+                        format!(" {}", artist.albums.len()),
+                        Style::default().fg(self.style.detail_color),
+                    ),
+                ])
+            })
+            .collect();
+
+        let albums: Vec<Line> = browser
+            .artist()
+            .map(|artist| artist.albums.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|album| {
+                let year = album
+                    .year
+                    .map(|year| format!("{} ", year))
+                    .unwrap_or_default();
+                Line::from(vec![
+                    Span::styled(year, Style::default().fg(self.style.detail_color)),
+                    Span::styled(
+                        album.title.clone(),
+                        Style::default().fg(self.style.album_color),
+                    ),
+                ])
+            })
+            .collect();
+
+        let tracks: Vec<Line> = browser
+            .album()
+            .map(|album| {
+                album
+                    .tracks
+                    .iter()
+                    .filter_map(|&index| Some((index, lib_state.tracks.get(index)?)))
+                    .map(|(index, track)| {
+                        let number = match (album.multi_disc, track.track_number) {
+                            (true, Some(number)) => {
+                                format!("{}-{:02} ", track.disc_number.unwrap_or(1), number)
+                            }
+                            (false, Some(number)) => format!("{:02} ", number),
+                            (_, None) => String::new(),
+                        };
+                        let style = if lib_state.selected_index == Some(index) {
+                            Style::default()
+                                .fg(self.style.playing_color)
+                                .add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default().fg(self.style.track_color)
+                        };
+                        Line::from(vec![
+                            Span::styled(number, Style::default().fg(self.style.detail_color)),
+                            Span::styled(track.title.clone(), style),
+                        ])
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for (column, (lines, chunk)) in [artists, albums, tracks]
+            .into_iter()
+            .zip(columns.iter())
+            .enumerate()
+        {
+            let last = column == 2;
+            let block = if last {
+                Block::default()
+            } else {
+                Block::default()
+                    .borders(Borders::RIGHT)
+                    .border_style(Style::default().fg(self.style.detail_color))
+            };
+            let column_inner = block.inner(*chunk);
+            frame.render_widget(block, *chunk);
+
+            // The cursor's column shows the cursor, the columns left of it
+            // the artist and album it is in
+            let focused = browser.column as usize == column;
+            let selected = browser.selected[column];
+            let selected_style = if focused {
+                Style::default()
+                    .fg(self.style.selected_fg_color)
+                    .bg(self.style.selected_bg_color)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+                    .bg(self.style.trail_bg_color)
+                    .add_modifier(Modifier::BOLD)
+            };
+            let shown = (column <= browser.column as usize).then_some(selected);
+            let cursor_row = (selected < lines.len()).then_some(selected);
+            // Columns without the cursor keep their selection in view
+            let mut column_viewport = ListViewport {
+                offset: selected.saturating_sub(column_inner.height as usize / 2),
+                ..Default::default()
+            };
+            let column_viewport = if focused {
+                &mut *viewport
+            } else {
+                &mut column_viewport
+            };
+            let visible =
+                column_viewport.visible_rows(lines.len(), cursor_row, column_inner.height as usize);
+            let items: Vec<ListItem> = lines
+                .into_iter()
+                .enumerate()
+                .skip(visible.start)
+                .take(visible.len())
+                .map(|(row, line)| {
+                    if shown == Some(row) {
+                        let spans: Vec<Span> = line
+                            .spans
+                            .into_iter()
+                            .map(|span| span.patch_style(selected_style))
+                            .collect();
+                        ListItem::new(Line::from(spans))
+                    } else {
+                        ListItem::new(line)
+                    }
+                })
+                .collect();
+            let list = if focused {
+                List::new(items).highlight_symbol("> ")
+            } else {
+                List::new(items)
+            };
+
+            // Scrollbars run along each column's right border, the last one
+            // along the pane's
+            let scroll_area = Rect {
+                y: area.y,
+                height: area.height,
+                width: chunk.width + u16::from(last),
+                ..*chunk
+            };
+            let scrollbar_color = if focused {
+                self.style.title_color
+            } else {
+                self.style.detail_color
+            };
+            render_scrolled(
+                frame,
+                scroll_area,
+                column_inner,
+                list,
+                cursor_row,
+                column_viewport,
+                scrollbar_color,
+            );
+        }
+
+        let album_count: usize = browser
+            .artists
+            .iter()
+            .map(|artist| artist.albums.len())
+            .sum();
+        let stats = match lib_state.scan_progress {
+            Some(files) => format!(" Scanning… {} files ", files),
+            None => format!(
+                " {} artists, {} albums ",
+                browser.artists.len(),
+                album_count
+            ),
+        };
+        let stats_style = Style::default()
+            .fg(self.style.stats_fg_color)
+            .bg(self.style.stats_bg_color);
+        let stats_area = Rect {
+            x: area.x + 1,
+            y: area.bottom() - 1,
+            width: area.width.saturating_sub(2),
+            height: 1,
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(Span::styled(stats, stats_style)))
+                .alignment(Alignment::Right),
+            stats_area,
+        );
+    }
+}
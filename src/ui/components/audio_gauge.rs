@@ -65,7 +65,8 @@ impl Widget for AudioGauge {
             available_width.saturating_sub(6) // Space for percentage
         } else {
             available_width
-        } - 5;
+        }
+        .saturating_sub(5);
 
         // Render label
         buf.set_string(area.x, area.y, &self.label, self.style);
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::controls::equalizer::{EQ_BAND_COUNT, EQ_FREQUENCIES};
+use crate::controls::graphic_eq::{GraphicEq, MAX_BAND_GAIN_DB};
+
+use super::audio_gauge::AudioGauge;
+
+struct EqualizerStyle {
+    gauge_color: Color,
+    text_color: Color,
+    focus_color: Color,
+    selected_color: Color,
+}
+
+impl Default for EqualizerStyle {
+    fn default() -> Self {
+        Self {
+            gauge_color: Color::White,
+            text_color: Color::Gray,
+            focus_color: Color::Cyan,
+            selected_color: Color::Yellow,
+        }
+    }
+}
+
+pub struct EqualizerUI {
+    style: EqualizerStyle,
+}
+
+impl EqualizerUI {
+    pub fn new() -> Self {
+        Self {
+            style: EqualizerStyle::default(),
+        }
+    }
+
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        eq_state: Arc<Mutex<GraphicEq>>,
+        focused: bool,
+    ) {
+        let (gains, selected_band, preset_name, naming) = {
+            let eq_state = eq_state.lock();
+            (
+                eq_state.gains,
+                eq_state.selected_band,
+                eq_state.preset_name(),
+                eq_state.naming.clone(),
+            )
+        };
+
+        let border_style = if focused {
+            Style::default().fg(self.style.focus_color)
+        } else {
+            Style::default()
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style)
+            .title(format!("Equalizer [{}]", preset_name));
+
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        // The name of a preset being saved is typed on the bottom border
+        if let Some(name) = naming {
+            let prompt = Paragraph::new(format!(" Save preset as: {}▏ ", name)).style(
+                Style::default()
+                    .fg(self.style.selected_color)
+                    .add_modifier(Modifier::BOLD),
+            );
+            let prompt_area = Rect {
+                x: area.x + 1,
+                y: area.bottom().saturating_sub(1),
+                width: area.width.saturating_sub(2),
+                height: 1,
+            };
+            frame.render_widget(prompt, prompt_area);
+        }
+
+        if inner.height == 0 {
+            return;
+        }
+
+        // Lay the bands out column-major in as many columns as the height requires
+        let rows = (inner.height as usize).min(EQ_BAND_COUNT);
+        let columns = EQ_BAND_COUNT.div_ceil(rows);
+        let column_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+            .split(inner);
+
+        for (band, gain) in gains.iter().enumerate() {
+            let column = column_chunks[band / rows];
+            let cell = Rect {
+                y: column.y + (band % rows) as u16,
+                height: 1,
+                ..column
+            };
+            self.render_band(frame, cell, band, *gain, focused && band == selected_band);
+        }
+    }
+
+    fn render_band(&self, frame: &mut Frame, area: Rect, band: usize, gain: f32, selected: bool) {
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(5), Constraint::Min(0)])
+            .split(area);
+
+        let label_style = if selected {
+            Style::default()
+                .fg(self.style.selected_color)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(self.style.text_color)
+        };
+        frame.render_widget(
+            Paragraph::new(format!("{:>4}", format_frequency(EQ_FREQUENCIES[band])))
+                .style(label_style),
+            layout[0],
+        );
+
+        let value = (gain + MAX_BAND_GAIN_DB) / (2.0 * MAX_BAND_GAIN_DB);
+        let value_text = format!("{:+3.0}dB", gain);
+        let band_widget = AudioGauge::new(value, value_text)
+            .style(Style::default().fg(self.style.gauge_color))
+            .show_percentage(false);
+
+        frame.render_widget(band_widget, layout[1]);
+    }
+}
+
+fn format_frequency(frequency: f32) -> String {
+    if frequency >= 1000.0 {
+        format!("{}k", frequency / 1000.0)
+    } else {
+        format!("{}", frequency)
+    }
+}
@@ -0,0 +1,171 @@
+use parking_lot::Mutex;
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+use std::sync::Arc;
+
+use crate::controls::folder_tree::{FolderTree, TreeRow};
+use crate::controls::list_viewport::ListViewport;
+use crate::controls::music_library::MusicLibrary;
+use crate::ui::components::music_library_ui::render_scrolled;
+
+/// The music directories as a tree of folders and tracks, in the library pane
+pub struct FolderTreeUI {
+    style: FolderTreeStyle,
+}
+
+struct FolderTreeStyle {
+    title_color: Color,
+    folder_color: Color,
+    count_color: Color,
+    track_color: Color,
+    playing_color: Color,
+    selected_bg_color: Color,
+    selected_fg_color: Color,
+    stats_bg_color: Color,
+    stats_fg_color: Color,
+}
+
+impl Default for FolderTreeStyle {
+    fn default() -> Self {
+        Self {
+            title_color: Color::Cyan,
+            folder_color: Color::LightBlue,
+            count_color: Color::DarkGray,
+            track_color: Color::White,
+            playing_color: Color::DarkGray,
+            selected_bg_color: Color::LightGreen,
+            selected_fg_color: Color::Black,
+            stats_bg_color: Color::Cyan,
+            stats_fg_color: Color::Black,
+        }
+    }
+}
+
+impl FolderTreeUI {
+    pub fn new() -> Self {
+        Self {
+            style: FolderTreeStyle::default(),
+        }
+    }
+
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        lib_state: Arc<Mutex<MusicLibrary>>,
+        tree: Arc<Mutex<FolderTree>>,
+        viewport: Arc<Mutex<ListViewport>>,
+    ) {
+        let lib_state = lib_state.lock();
+        let tree = tree.lock();
+        let mut viewport = viewport.lock();
+
+        let block = Block::default()
+            .title("Music Library: Folders")
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(self.style.title_color));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let cursor_style = Style::default()
+            .fg(self.style.selected_fg_color)
+            .bg(self.style.selected_bg_color)
+            .add_modifier(Modifier::BOLD);
+
+        let cursor_row = (tree.selected < tree.rows.len()).then_some(tree.selected);
+        let visible = viewport.visible_rows(tree.rows.len(), cursor_row, inner.height as usize);
+        let items: Vec<ListItem> = tree
+            .rows
+            .iter()
+            .enumerate()
+            .skip(visible.start)
+            .take(visible.len())
+            .map(|(i, row)| {
+                let indent = Span::raw("  ".repeat(row.depth()));
+                let line = match row {
+                    TreeRow::Folder {
+                        path,
+                        expanded,
+                        tracks,
+                        ..
+                    } => {
+                        let marker = if *expanded { "▾ " } else { "▸ " };
+                        let name = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+                        let style = if i == tree.selected {
+                            cursor_style
+                        } else {
+                            Style::default()
+                                .fg(self.style.folder_color)
+                                .add_modifier(Modifier::BOLD)
+                        };
+                        Line::from(vec![
+                            indent,
+                            Span::styled(marker, Style::default().fg(self.style.folder_color)),
+                            Span::styled(format!("{}/", name), style),
+                            Span::styled(
+                                format!(" {}", tracks),
+                                Style::default().fg(self.style.count_color),
+                            ),
+                        ])
+                    }
+                    TreeRow::Track { index, .. } => {
+                        let name = lib_state
+                            .tracks
+                            .get(*index)
+                            .map(|track| track.display_name())
+                            .unwrap_or_default();
+                        let style = if i == tree.selected {
+                            cursor_style
+                        } else if lib_state.selected_index == Some(*index) {
+                            Style::default()
+                                .fg(self.style.playing_color)
+                                .add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default().fg(self.style.track_color)
+                        };
+                        Line::from(vec![indent, Span::raw("  "), Span::styled(name, style)])
+                    }
+                };
+                ListItem::new(line)
+            })
+            .collect();
+
+        render_scrolled(
+            frame,
+            area,
+            inner,
+            List::new(items).highlight_symbol("> "),
+            cursor_row,
+            &viewport,
+            self.style.title_color,
+        );
+
+        let stats = match lib_state.scan_progress {
+            Some(files) => format!(" Scanning… {} files ", files),
+            None => format!(" {} tracks ", lib_state.tracks.len()),
+        };
+        let stats_style = Style::default()
+            .fg(self.style.stats_fg_color)
+            .bg(self.style.stats_bg_color);
+        let stats_area = Rect {
+            x: area.x + 1,
+            y: area.bottom() - 1,
+            width: area.width.saturating_sub(2),
+            height: 1,
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(Span::styled(stats, stats_style)))
+                .alignment(Alignment::Right),
+            stats_area,
+        );
+    }
+}
@@ -36,6 +36,8 @@ impl HelpUI {
         let mut bindings: Vec<(String, String)> = keybindings
             .get_all_bindings()
             .iter()
+            .chain(keybindings.get_equalizer_bindings())
+            .chain(keybindings.get_queue_bindings())
             .map(|(key, action)| {
                 let key_str = Keybindings::key_event_to_string(key);
                 (key_str, action.description.clone())
@@ -120,7 +122,7 @@ impl HelpUI {
     }
 
     /// Helper function to create a centered rect using a percentage of the available rect
-    fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         let popup_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -1,6 +1,12 @@
+pub mod album_browser_ui;
 pub mod audio_gauge;
+pub mod equalizer_ui;
+pub mod folder_tree_ui;
 pub mod help_ui;
 pub mod music_library_ui;
+pub mod play_queue_ui;
 pub mod playback_control_ui;
+pub mod playlist_sidebar_ui;
+pub mod scan_report_ui;
 pub mod sound_control_ui;
 pub mod visualizer_ui;
@@ -1,14 +1,20 @@
 use parking_lot::Mutex;
 use ratatui::{
-    layout::{Alignment, Rect},
+    layout::{Alignment, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{
+        Block, Borders, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState,
+    },
     Frame,
 };
 use std::sync::Arc;
 
-use crate::controls::music_library::MusicLibrary;
+use crate::controls::library_search::{field_text, LibrarySearch, MatchField, SearchMatch};
+use crate::controls::list_viewport::ListViewport;
+use crate::controls::music_library::{MusicLibrary, Track};
+use crate::controls::playlist::Playlists;
 
 pub struct MusicLibraryUI {
     style: MusicLibraryStyle,
@@ -19,11 +25,14 @@ struct MusicLibraryStyle {
     number_color: Color,
     filename_color: Color,
     extension_color: Color,
+    source_color: Color,
     highlight_bg_color: Color,
     selected_bg_color: Color,
     selected_fg_color: Color,
     stats_bg_color: Color,
     stats_fg_color: Color,
+    match_color: Color,
+    prompt_color: Color,
 }
 
 impl Default for MusicLibraryStyle {
@@ -33,11 +42,14 @@ impl Default for MusicLibraryStyle {
             number_color: Color::Yellow,
             filename_color: Color::White,
             extension_color: Color::DarkGray,
+            source_color: Color::LightBlue,
             highlight_bg_color: Color::DarkGray,
             selected_bg_color: Color::LightGreen,
             selected_fg_color: Color::Black,
             stats_bg_color: Color::Cyan,
             stats_fg_color: Color::Black,
+            match_color: Color::Yellow,
+            prompt_color: Color::Yellow,
         }
     }
 }
@@ -49,11 +61,36 @@ impl MusicLibraryUI {
         }
     }
 
-    pub fn render(&self, frame: &mut Frame, area: Rect, lib_state: Arc<Mutex<MusicLibrary>>) {
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        lib_state: Arc<Mutex<MusicLibrary>>,
+        playlists: Arc<Mutex<Playlists>>,
+        search: Arc<Mutex<LibrarySearch>>,
+        viewport: Arc<Mutex<ListViewport>>,
+    ) {
         let lib_state = lib_state.lock();
+        let playlists = playlists.lock();
+        let search = search.lock();
+        let mut viewport = viewport.lock();
+        if playlists.shown().is_some() {
+            self.render_playlist(frame, area, &lib_state, &playlists, &mut viewport);
+            return;
+        }
 
+        let filtered = if search.filter && search.is_active() {
+            " (filtered)"
+        } else {
+            ""
+        };
+        let title = format!(
+            "Music Library [Sort: {}]{}",
+            lib_state.sort.label(),
+            filtered
+        );
         let block = Block::default()
-            .title("Music Library")
+            .title(title)
             .borders(Borders::ALL)
             .border_type(ratatui::widgets::BorderType::Rounded)
             .border_style(Style::default().fg(self.style.title_color));
@@ -64,9 +101,15 @@ impl MusicLibraryUI {
         let current_track_idx = lib_state.current_index;
         let selected_track_idx = lib_state.selected_index;
 
-        let items: Vec<ListItem> = tracks
+        // Filtering hides tracks but keeps their numbers, which are their
+        // places in the library
+        let listed: Vec<usize> = (0..tracks.len()).filter(|&i| search.shows(i)).collect();
+        let cursor_row = listed.iter().position(|&i| i == current_track_idx);
+        // Only the rows in view are built
+        let visible = viewport.visible_rows(listed.len(), cursor_row, inner.height as usize);
+        let items: Vec<ListItem> = listed[visible]
             .iter()
-            .enumerate()
+            .map(|&i| (i, &tracks[i]))
             .map(|(i, track)| {
                 // Track number styling
                 let number = Span::styled(
@@ -74,25 +117,27 @@ impl MusicLibraryUI {
                     Style::default().fg(self.style.number_color),
                 );
 
-                // Filename styling with enhanced selection and current track handling
-                let file_name = if i == current_track_idx {
-                    Span::styled(
-                        &track.title,
-                        Style::default()
-                            .fg(self.style.selected_fg_color)
-                            .bg(self.style.selected_bg_color)
-                            .add_modifier(Modifier::BOLD),
-                    )
+                // Name styling with enhanced selection and current track handling
+                let name_style = if i == current_track_idx {
+                    Style::default()
+                        .fg(self.style.selected_fg_color)
+                        .bg(self.style.selected_bg_color)
+                        .add_modifier(Modifier::BOLD)
                 } else if Some(i) == selected_track_idx {
-                    Span::styled(
-                        &track.title,
-                        Style::default()
-                            .fg(self.style.highlight_bg_color)
-                            .add_modifier(Modifier::BOLD),
-                    )
+                    Style::default()
+                        .fg(self.style.highlight_bg_color)
+                        .add_modifier(Modifier::BOLD)
                 } else {
-                    Span::styled(&track.title, Style::default().fg(self.style.filename_color))
+                    Style::default().fg(self.style.filename_color)
                 };
+                let mut spans = vec![number];
+                spans.extend(self.name_spans(
+                    track,
+                    &lib_state,
+                    search.match_for(i),
+                    name_style,
+                    i == current_track_idx,
+                ));
 
                 // Extension styling
                 let extension = Span::styled(
@@ -110,24 +155,51 @@ impl MusicLibraryUI {
                         .add_modifier(Modifier::ITALIC),
                 );
 
-                ListItem::new(Line::from(vec![number, file_name, extension]))
+                spans.push(extension);
+
+                // With several music directories, the one the track is from
+                if lib_state.roots.dirs.len() > 1 {
+                    if let Some(source) = lib_state.roots.source_of(&track.path) {
+                        spans.push(Span::styled(
+                            format!(" @{}", source),
+                            Style::default().fg(self.style.source_color),
+                        ));
+                    }
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
         let list = List::new(items)
             .style(Style::default())
-            .highlight_symbol("> ") // Optional: add a selector
-            .highlight_style(
-                Style::default()
-                    .bg(self.style.highlight_bg_color)
-                    .add_modifier(Modifier::BOLD),
-            );
+            .highlight_symbol("> "); // Marks the cursor row
 
-        frame.render_widget(list, inner);
+        render_scrolled(
+            frame,
+            area,
+            inner,
+            list,
+            cursor_row,
+            &viewport,
+            self.style.title_color,
+        );
 
-        // Stats rendering remains the same
-        if !tracks.is_empty() {
-            let stats = format!(" {} tracks ", tracks.len());
+        // Stats rendering: scan progress while scanning, otherwise the track
+        // count with a hint at the scan report when files were skipped
+        if !tracks.is_empty() || !lib_state.skipped.is_empty() || lib_state.scan_progress.is_some()
+        {
+            let stats = match (lib_state.scan_progress, lib_state.skipped.len()) {
+                (Some(files), _) => format!(" Scanning… {} files ", files),
+                _ if search.is_active() => {
+                    format!(
+                        " {} of {} tracks match ",
+                        search.matches.len(),
+                        tracks.len()
+                    )
+                }
+                (None, 0) => format!(" {} tracks ", tracks.len()),
+                (None, skipped) => format!(" {} tracks, {} skipped (i) ", tracks.len(), skipped),
+            };
             let stats_style = Style::default()
                 .fg(self.style.stats_fg_color)
                 .bg(self.style.stats_bg_color);
@@ -135,7 +207,7 @@ impl MusicLibraryUI {
             let stats_area = Rect {
                 x: area.x + 1,
                 y: area.bottom() - 1,
-                width: area.width - 2,
+                width: area.width.saturating_sub(2),
                 height: 1,
             };
             frame.render_widget(
@@ -143,5 +215,229 @@ impl MusicLibraryUI {
                 stats_area,
             );
         }
+
+        // The query sits on the left of the bottom border, like vim's
+        if search.editing || search.is_active() {
+            let cursor = if search.editing { "▏" } else { "" };
+            let prompt = Span::styled(
+                format!(" /{}{} ", search.query, cursor),
+                Style::default()
+                    .fg(self.style.prompt_color)
+                    .add_modifier(Modifier::BOLD),
+            );
+            let prompt_area = Rect {
+                x: area.x + 1,
+                y: area.bottom() - 1,
+                width: area.width.saturating_sub(2),
+                height: 1,
+            };
+            frame.render_widget(Paragraph::new(Line::from(prompt)), prompt_area);
+        }
+    }
+
+    /// The name of a track with the characters a search matched highlighted.
+    /// When the match is in the album or path, that text follows the name.
+    fn name_spans(
+        &self,
+        track: &Track,
+        library: &MusicLibrary,
+        search_match: Option<&SearchMatch>,
+        style: Style,
+        is_cursor: bool,
+    ) -> Vec<Span<'static>> {
+        let match_style = if is_cursor {
+            style.add_modifier(Modifier::UNDERLINED)
+        } else {
+            style
+                .fg(self.style.match_color)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        };
+        let Some(search_match) = search_match else {
+            return vec![Span::styled(track.display_name(), style)];
+        };
+
+        let matched_text = field_text(track, search_match.field, library);
+        let positions = &search_match.positions;
+        if search_match.field == MatchField::Name {
+            return highlighted(&matched_text, positions, style, match_style);
+        }
+        let dim = Style::default()
+            .fg(self.style.extension_color)
+            .add_modifier(Modifier::ITALIC);
+        let mut spans = vec![
+            Span::styled(track.display_name(), style),
+            Span::styled(" · ", dim),
+        ];
+        spans.extend(highlighted(&matched_text, positions, dim, match_style));
+        spans
+    }
+
+    /// The entries of the shown playlist, in the library's look. Entries whose
+    /// file is not in the library are greyed out.
+    fn render_playlist(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        lib_state: &MusicLibrary,
+        playlists: &Playlists,
+        viewport: &mut ListViewport,
+    ) {
+        let (Some(view), Some(playlist)) = (playlists.view, playlists.shown()) else {
+            return;
+        };
+        let selected_entry = playlists.selected_entry;
+        let playing_entry = playlists
+            .playing
+            .filter(|(playing, _)| *playing == view)
+            .map(|(_, entry)| entry);
+
+        let block = Block::default()
+            .title(format!("Playlist: {}", playlist.name))
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(self.style.title_color));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let cursor_row = (selected_entry < playlist.entries.len()).then_some(selected_entry);
+        let visible =
+            viewport.visible_rows(playlist.entries.len(), cursor_row, inner.height as usize);
+        let items: Vec<ListItem> = playlist
+            .entries
+            .iter()
+            .enumerate()
+            .skip(visible.start)
+            .take(visible.len())
+            .map(|(i, entry)| {
+                let number = Span::styled(
+                    format!("{:2}.", i + 1),
+                    Style::default().fg(self.style.number_color),
+                );
+
+                let track = lib_state
+                    .index_of(&entry.path)
+                    .map(|index| &lib_state.tracks[index]);
+                let name = match track {
+                    Some(track) => track.display_name(),
+                    None => entry.display_name(),
+                };
+                let name = if i == selected_entry {
+                    Span::styled(
+                        name,
+                        Style::default()
+                            .fg(self.style.selected_fg_color)
+                            .bg(self.style.selected_bg_color)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                } else if track.is_none() {
+                    Span::styled(
+                        name,
+                        Style::default()
+                            .fg(self.style.extension_color)
+                            .add_modifier(Modifier::CROSSED_OUT),
+                    )
+                } else if Some(i) == playing_entry {
+                    Span::styled(
+                        name,
+                        Style::default()
+                            .fg(self.style.highlight_bg_color)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    Span::styled(name, Style::default().fg(self.style.filename_color))
+                };
+
+                ListItem::new(Line::from(vec![number, name]))
+            })
+            .collect();
+
+        render_scrolled(
+            frame,
+            area,
+            inner,
+            List::new(items).highlight_symbol("> "),
+            cursor_row,
+            viewport,
+            self.style.title_color,
+        );
+
+        let stats_style = Style::default()
+            .fg(self.style.stats_fg_color)
+            .bg(self.style.stats_bg_color);
+        let stats_text = Line::from(vec![Span::styled(
+            format!(" {} entries ", playlist.entries.len()),
+            stats_style,
+        )]);
+        let stats_area = Rect {
+            x: area.x + 1,
+            y: area.bottom() - 1,
+            width: area.width.saturating_sub(2),
+            height: 1,
+        };
+        frame.render_widget(
+            Paragraph::new(stats_text).alignment(Alignment::Right),
+            stats_area,
+        );
+    }
+}
+
+/// Render the rows of a track panel that `ListViewport::visible_rows` picked,
+/// with a scrollbar on the right border when not all rows fit. `list` holds
+/// only the visible rows; `cursor_row` counts from the top of the whole list.
+pub fn render_scrolled(
+    frame: &mut Frame,
+    area: Rect,
+    inner: Rect,
+    list: List,
+    cursor_row: Option<usize>,
+    viewport: &ListViewport,
+    scrollbar_color: Color,
+) {
+    let mut list_state = ListState::default()
+        .with_selected(cursor_row.and_then(|row| row.checked_sub(viewport.offset)));
+    frame.render_stateful_widget(list, inner, &mut list_state);
+
+    if viewport.rows > viewport.height {
+        let mut scrollbar_state =
+            ScrollbarState::new(viewport.rows).position(cursor_row.unwrap_or(viewport.offset));
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .style(Style::default().fg(scrollbar_color));
+        frame.render_stateful_widget(
+            scrollbar,
+            area.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+    }
+}
+
+/// Split a text into spans, with the characters at `positions` in `match_style`
+fn highlighted(
+    text: &str,
+    positions: &[usize],
+    style: Style,
+    match_style: Style,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    let mut positions = positions.iter().peekable();
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.next_if(|&&position| position == i).is_some();
+        if matched != run_matched && !run.is_empty() {
+            let run_style = if run_matched { match_style } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), run_style));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        let run_style = if run_matched { match_style } else { style };
+        spans.push(Span::styled(run, run_style));
     }
+    spans
 }
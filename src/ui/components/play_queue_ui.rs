@@ -0,0 +1,131 @@
+use parking_lot::Mutex;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use std::sync::Arc;
+
+use crate::controls::music_library::MusicLibrary;
+use crate::controls::play_queue::PlayQueue;
+
+/// The tracks lined up to play next, in order
+pub struct PlayQueueUI {
+    style: PlayQueueStyle,
+}
+
+struct PlayQueueStyle {
+    border_color: Color,
+    focus_color: Color,
+    number_color: Color,
+    name_color: Color,
+    missing_color: Color,
+    selected_fg_color: Color,
+    selected_bg_color: Color,
+    hint_color: Color,
+}
+
+impl Default for PlayQueueStyle {
+    fn default() -> Self {
+        Self {
+            border_color: Color::Cyan,
+            focus_color: Color::Yellow,
+            number_color: Color::Yellow,
+            name_color: Color::White,
+            missing_color: Color::DarkGray,
+            selected_fg_color: Color::Black,
+            selected_bg_color: Color::LightGreen,
+            hint_color: Color::DarkGray,
+        }
+    }
+}
+
+impl PlayQueueUI {
+    pub fn new() -> Self {
+        Self {
+            style: PlayQueueStyle::default(),
+        }
+    }
+
+    /// Height the panel needs to list every queued track, borders included
+    pub fn height(play_queue: &PlayQueue) -> u16 {
+        play_queue.entries.len().max(1) as u16 + 2
+    }
+
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        play_queue: Arc<Mutex<PlayQueue>>,
+        library: Arc<Mutex<MusicLibrary>>,
+        focused: bool,
+    ) {
+        let play_queue = play_queue.lock();
+        let library = library.lock();
+
+        let border_color = if focused {
+            self.style.focus_color
+        } else {
+            self.style.border_color
+        };
+        let block = Block::default()
+            .title(format!("Queue ({})", play_queue.entries.len()))
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(border_color));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        if play_queue.entries.is_empty() {
+            let hint = Paragraph::new("Empty, u adds the selected track").style(
+                Style::default()
+                    .fg(self.style.hint_color)
+                    .add_modifier(Modifier::ITALIC),
+            );
+            frame.render_widget(hint, inner);
+            return;
+        }
+
+        let items: Vec<ListItem> = play_queue
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let number = Span::styled(
+                    format!("{:2}.", i + 1),
+                    Style::default().fg(self.style.number_color),
+                );
+
+                let track = library.index_of(path).map(|index| &library.tracks[index]);
+                let name = match track {
+                    Some(track) => track.display_name(),
+                    None => path
+                        .file_stem()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                };
+                let style = if focused && i == play_queue.selected {
+                    Style::default()
+                        .fg(self.style.selected_fg_color)
+                        .bg(self.style.selected_bg_color)
+                        .add_modifier(Modifier::BOLD)
+                } else if track.is_none() {
+                    Style::default()
+                        .fg(self.style.missing_color)
+                        .add_modifier(Modifier::CROSSED_OUT)
+                } else {
+                    Style::default().fg(self.style.name_color)
+                };
+
+                ListItem::new(Line::from(vec![number, Span::styled(name, style)]))
+            })
+            .collect();
+
+        // Scroll along with the selection while the panel has focus
+        let mut list_state =
+            ListState::default().with_selected(focused.then_some(play_queue.selected));
+        frame.render_stateful_widget(List::new(items), inner, &mut list_state);
+    }
+}
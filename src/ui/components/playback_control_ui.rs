@@ -64,9 +64,18 @@ impl PlaybackControlUI {
     ) {
         let playback_state = playback_state.lock();
 
-        let block = Block::default()
-            .borders(Borders::ALL)
-            .title("Playback Controls");
+        let mut title = format!(
+            "Playback Controls [Repeat: {}] [Shuffle: {}]",
+            playback_state.repeat.label(),
+            playback_state.shuffle.label()
+        );
+        if playback_state.crossfade_enabled {
+            title.push_str(&format!(
+                " [Crossfade {}s]",
+                playback_state.crossfade.as_secs_f32()
+            ));
+        }
+        let block = Block::default().borders(Borders::ALL).title(title);
 
         let inner = block.inner(area);
         frame.render_widget(block, area);
@@ -0,0 +1,102 @@
+use parking_lot::Mutex;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+use std::sync::Arc;
+
+use crate::controls::playlist::Playlists;
+
+/// Lists "Library" and the saved playlists, highlighting the one shown in the
+/// track list and marking the playlist new tracks are added to
+pub struct PlaylistSidebarUI {
+    style: PlaylistSidebarStyle,
+}
+
+struct PlaylistSidebarStyle {
+    border_color: Color,
+    name_color: Color,
+    count_color: Color,
+    shown_fg_color: Color,
+    shown_bg_color: Color,
+    target_color: Color,
+}
+
+impl Default for PlaylistSidebarStyle {
+    fn default() -> Self {
+        Self {
+            border_color: Color::Cyan,
+            name_color: Color::White,
+            count_color: Color::DarkGray,
+            shown_fg_color: Color::Black,
+            shown_bg_color: Color::Cyan,
+            target_color: Color::Yellow,
+        }
+    }
+}
+
+impl PlaylistSidebarUI {
+    pub fn new() -> Self {
+        Self {
+            style: PlaylistSidebarStyle::default(),
+        }
+    }
+
+    /// Height the sidebar needs to list every playlist, borders included
+    pub fn height(playlists: &Playlists) -> u16 {
+        playlists.playlists.len() as u16 + 3
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, playlists: Arc<Mutex<Playlists>>) {
+        let playlists = playlists.lock();
+
+        let block = Block::default()
+            .title("Playlists (Tab)")
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(self.style.border_color));
+
+        let shown_style = Style::default()
+            .fg(self.style.shown_fg_color)
+            .bg(self.style.shown_bg_color)
+            .add_modifier(Modifier::BOLD);
+        let name_style = Style::default().fg(self.style.name_color);
+
+        let library_style = if playlists.view.is_none() {
+            shown_style
+        } else {
+            name_style
+        };
+        let mut items = vec![ListItem::new(Line::from(vec![
+            Span::raw("  "),
+            Span::styled("Library", library_style),
+        ]))];
+
+        items.extend(playlists.playlists.iter().enumerate().map(|(i, playlist)| {
+            let style = if playlists.view == Some(i) {
+                shown_style
+            } else {
+                name_style
+            };
+            let marker = if playlists.target == Some(i) {
+                Span::styled("+ ", Style::default().fg(self.style.target_color))
+            } else {
+                Span::raw("  ")
+            };
+            let count = Span::styled(
+                format!(" {}", playlist.entries.len()),
+                Style::default().fg(self.style.count_color),
+            );
+            ListItem::new(Line::from(vec![
+                marker,
+                Span::styled(playlist.name.clone(), style),
+                count,
+            ]))
+        }));
+
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
@@ -0,0 +1,96 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table},
+    Frame,
+};
+
+use crate::controls::library_roots::LibraryRoots;
+use crate::controls::music_library::SkippedFile;
+
+use super::help_ui::HelpUI;
+
+/// Overlay listing the files the library scan skipped, with the reasons
+pub struct ScanReportUI;
+
+impl ScanReportUI {
+    pub fn render(frame: &mut Frame, skipped: &[SkippedFile], roots: &LibraryRoots) {
+        // Dimmed full-screen backdrop
+        let backdrop = Block::default().style(Style::default().add_modifier(Modifier::DIM));
+        frame.render_widget(backdrop, frame.area());
+
+        let report_area = HelpUI::centered_rect(80, 70, frame.area());
+
+        let block = Block::default()
+            .title(format!(" Scan Report: {} skipped ", skipped.len()))
+            .title_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+
+        let inner = block.inner(report_area);
+        frame.render_widget(Clear, report_area);
+        frame.render_widget(block, report_area);
+
+        if skipped.is_empty() {
+            let message = Paragraph::new("Every file in the library was read successfully")
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(Color::Green));
+            frame.render_widget(message, inner);
+        } else {
+            let row_style_odd = Style::default().bg(Color::Rgb(35, 37, 45));
+            let rows: Vec<Row> = skipped
+                .iter()
+                .enumerate()
+                .map(|(i, file)| {
+                    // Paths are shown relative to their library root
+                    let path = roots.display_path(&file.path);
+                    let row = Row::new(vec![
+                        Cell::from(path.display().to_string()),
+                        Cell::from(file.reason.clone()).style(Style::default().fg(Color::Red)),
+                    ]);
+                    if i % 2 == 1 {
+                        row.style(row_style_odd)
+                    } else {
+                        row
+                    }
+                })
+                .collect();
+
+            let header_style = Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD);
+            let table = Table::new(
+                rows,
+                [Constraint::Percentage(50), Constraint::Percentage(50)],
+            )
+            .header(
+                Row::new(vec![
+                    Cell::from("File").style(header_style),
+                    Cell::from("Reason").style(header_style),
+                ])
+                .bottom_margin(1),
+            );
+            frame.render_widget(table, inner);
+        }
+
+        // Add hint at the bottom of the screen
+        let hint_text = "Press any key to close the report";
+        let hint_area = Rect::new(
+            (frame.area().width.saturating_sub(hint_text.len() as u16)) / 2,
+            frame.area().height.saturating_sub(2),
+            (hint_text.len() as u16).min(frame.area().width),
+            1,
+        );
+        let hint = Paragraph::new(hint_text).style(
+            Style::default()
+                .fg(Color::Gray)
+                .add_modifier(Modifier::ITALIC),
+        );
+        frame.render_widget(Clear, hint_area);
+        frame.render_widget(hint, hint_area);
+    }
+}
@@ -27,6 +27,7 @@ pub enum AudioControlType {
     Bass,
     Treble,
     Pitch,
+    Tempo,
 }
 
 impl Default for ControlStyle {
@@ -46,9 +47,11 @@ impl SoundControlUI {
     }
 
     pub fn render(&self, frame: &mut Frame, area: Rect, sound_state: Arc<Mutex<SoundControl>>) {
-        let block = Block::default()
-            .borders(Borders::ALL)
-            .title("Audio Controls");
+        let sound_state = sound_state.lock();
+        let block = Block::default().borders(Borders::ALL).title(format!(
+            "Audio Controls [ReplayGain: {}]",
+            sound_state.replay_gain_mode().label()
+        ));
 
         let inner = block.inner(area);
         frame.render_widget(block, area);
@@ -56,31 +59,63 @@ impl SoundControlUI {
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
-                Constraint::Percentage(25),
-                Constraint::Percentage(25),
-                Constraint::Percentage(25),
-                Constraint::Percentage(25),
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
             ])
             .split(inner);
 
-        let sound_state = sound_state.lock();
+        let percent = |value: f32| format!("{:3}%", value as u8);
         let controls = [
-            (AudioControlType::Volume, sound_state.volume() / 100.0),
-            (AudioControlType::Bass, sound_state.bass() / 100.0),
-            (AudioControlType::Treble, sound_state.treble() / 100.0),
+            (
+                AudioControlType::Volume,
+                sound_state.volume() / 100.0,
+                percent(sound_state.volume()),
+            ),
+            (
+                AudioControlType::Bass,
+                sound_state.bass() / 100.0,
+                percent(sound_state.bass()),
+            ),
+            (
+                AudioControlType::Treble,
+                sound_state.treble() / 100.0,
+                percent(sound_state.treble()),
+            ),
             (
                 AudioControlType::Pitch,
-                (sound_state.pitch() + 100.0) / 200.0,
+                (sound_state.pitch() + 12.0) / 24.0,
+                format!("{:+3}st", sound_state.pitch() as i8),
+            ),
+            (
+                AudioControlType::Tempo,
+                (sound_state.tempo() - 50.0) / 150.0,
+                percent(sound_state.tempo()),
             ),
         ];
         drop(sound_state);
 
-        for (i, (control_type, value)) in controls.iter().enumerate() {
-            self.render_gauge(frame, chunks[i], &control_type.to_string(), *value);
+        for (i, (control_type, value, value_text)) in controls.iter().enumerate() {
+            self.render_gauge(
+                frame,
+                chunks[i],
+                &control_type.to_string(),
+                *value,
+                value_text,
+            );
         }
     }
 
-    fn render_gauge(&self, frame: &mut Frame, area: Rect, label: &str, value: f32) {
+    fn render_gauge(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        label: &str,
+        value: f32,
+        value_text: &str,
+    ) {
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -95,7 +130,6 @@ impl SoundControlUI {
             layout[0],
         );
 
-        let value_text = format!("{:3}%", (value * 100.0) as u8);
         let audio_control_widget = AudioGauge::new(value, value_text.to_string())
             .style(Style::default().fg(self.style.gauge_color))
             .show_percentage(false);
@@ -111,6 +145,7 @@ impl std::fmt::Display for AudioControlType {
             AudioControlType::Bass => write!(f, "Bass"),
             AudioControlType::Treble => write!(f, "Treble"),
             AudioControlType::Pitch => write!(f, "Pitch"),
+            AudioControlType::Tempo => write!(f, "Tempo"),
         }
     }
 }
@@ -1,5 +1,4 @@
 use core::f64;
-use std::process::Command;
 
 use ratatui::layout::Alignment;
 use ratatui::{
@@ -14,8 +13,14 @@ use ratatui::{
 };
 use rustfft::num_complex::ComplexFloat;
 
+use crate::controls::band_mapping::{BandMapper, BandSettings, Bands};
+use crate::controls::native_bars::{BarSettings, NativeBars};
+use crate::controls::spectrum::SpectrumFrame;
+
 pub struct VisualizerUI {
     style: VisualizerStyle,
+    band_mapper: BandMapper,
+    native_bars: NativeBars,
 }
 
 pub struct VisualizerStyle {
@@ -40,14 +45,26 @@ impl Default for VisualizerStyle {
 
 type CanvasPainter<'a> = Box<dyn Fn(&mut Context) + 'a>;
 
+/// Columns per bar of the bar visualizer: two for the bar and one for the gap
+const BAR_STRIDE: usize = 3;
+
 impl VisualizerUI {
-    pub fn new() -> Self {
+    pub fn new(band_settings: BandSettings, bar_settings: BarSettings) -> Self {
         Self {
             style: VisualizerStyle::default(),
+            band_mapper: BandMapper::new(band_settings),
+            native_bars: NativeBars::new(bar_settings),
         }
     }
 
-    pub fn render(&self, frame: &mut Frame, area: Rect, spectrum: Vec<f32>, canvas_type: usize) {
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        spectrum: SpectrumFrame,
+        canvas_type: usize,
+        error: Option<String>,
+    ) {
         let time = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -58,27 +75,39 @@ impl VisualizerUI {
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
             .title_alignment(Alignment::Center)
-            .title("Spectrum Visualizer")
+            .title(match error {
+                Some(error) => format!("Spectrum Visualizer [unavailable: {}]", error),
+                None => "Spectrum Visualizer".to_string(),
+            })
             .style(self.style.block_style);
 
         // Render the outer block and compute the inner area for the canvas
         let inner_area = block.inner(area);
         frame.render_widget(block, area);
 
+        // Modes without a canvas of their own show the bars
+        let canvas_type = if canvas_type <= 4 { canvas_type } else { 0 };
+
+        // Every canvas draws from the same bands, as many as it has room for
+        let band_count = match canvas_type {
+            0 => inner_area.width as usize / BAR_STRIDE,
+            1 | 4 => inner_area.width as usize / 2,
+            2 => inner_area.width as usize / 3,
+            _ => inner_area.width as usize,
+        };
+        self.band_mapper.update(&spectrum, band_count);
+        let bands = self.band_mapper.bands();
+        if canvas_type == 0 {
+            self.native_bars.update(&bands.values);
+        }
+
         // Select the appropriate canvas based on `canvas_type`
         let canvas = match canvas_type {
-            0 => self.get_canvas_cava(inner_area, &spectrum, time),
-            1 => self.get_canvas_1(inner_area, &spectrum, time),
-            2 => self.get_canvas_2(inner_area, &spectrum, time),
-            3 => self.get_canvas_3(inner_area, &spectrum, time),
-            4 => self.get_canvas_4(inner_area, &spectrum, time),
-            _ => {
-                if is_cava_installed() {
-                    self.get_canvas_cava(inner_area, &spectrum, time)
-                } else {
-                    self.get_canvas_4(inner_area, &spectrum, time)
-                }
-            }
+            1 => self.get_canvas_1(inner_area, bands, time),
+            2 => self.get_canvas_2(inner_area, bands, time),
+            3 => self.get_canvas_3(inner_area, bands, time),
+            4 => self.get_canvas_4(inner_area, bands, time),
+            _ => self.get_canvas_bars(inner_area),
         };
 
         // Render the selected canvas
@@ -91,7 +120,7 @@ impl VisualizerUI {
     fn get_canvas_1<'a>(
         &'a self,
         inner_area: Rect,
-        spectrum: &'a [f32],
+        bands: &'a Bands,
         time: f64,
     ) -> Canvas<CanvasPainter<'a>> {
         Canvas::default()
@@ -113,42 +142,10 @@ impl VisualizerUI {
                     color: Color::Rgb(10, 10, 20),
                 });
 
-                // Use static data for smoothing
-                use std::sync::Mutex;
-                use std::sync::OnceLock;
-
-                static SMOOTHED_VALUES: OnceLock<Mutex<Vec<f64>>> = OnceLock::new();
-                static PEAKS: OnceLock<Mutex<Vec<f64>>> = OnceLock::new();
-
-                // Number of bands to visualize (half the width to allow for mirroring)
-                let num_bands = (inner_area.width as usize / 2).min(spectrum.len());
-
-                let smoothed = SMOOTHED_VALUES.get_or_init(|| Mutex::new(vec![0.0; num_bands]));
-                let peaks = PEAKS.get_or_init(|| Mutex::new(vec![0.0; num_bands]));
-
-                // Update smoothed values and peaks
-                {
-                    let mut smoothed_data = smoothed.lock().unwrap();
-                    let mut peaks_data = peaks.lock().unwrap();
-
-                    for (i, &value) in spectrum.iter().enumerate().take(num_bands) {
-                        // Apply smoothing factor (lower = smoother)
-                        let alpha = 0.15;
-                        let normalized_value = f64::from(value).powf(0.7);
-                        smoothed_data[i] =
-                            smoothed_data[i] * (1.0 - alpha) + normalized_value * alpha;
-
-                        // Update peak values with slow decay
-                        if smoothed_data[i] > peaks_data[i] {
-                            peaks_data[i] = smoothed_data[i];
-                        } else {
-                            peaks_data[i] *= 0.98; // Slow decay for peaks
-                        }
-                    }
-                }
-
-                let smoothed_values = smoothed.lock().unwrap().clone();
-                let peak_values = peaks.lock().unwrap().clone();
+                // Bands cover half the width, the other half mirrors them
+                let num_bands = bands.values.len();
+                let smoothed_values: Vec<f64> = bands.values.iter().map(|&v| v.into()).collect();
+                let peak_values: Vec<f64> = bands.peaks.iter().map(|&v| v.into()).collect();
 
                 // Draw smooth centerline with wave effect
                 let wave_speed = 0.3; // Slower wave speed
@@ -305,115 +302,13 @@ impl VisualizerUI {
             }))
     }
 
-    #[allow(dead_code, elided_named_lifetimes)]
-    fn get_canvas_cava<'a>(
-        &'a self,
-        inner_area: Rect,
-        _spectrum: &'a [f32], // Not used with CAVA
-        time: f64,
-    ) -> Canvas<CanvasPainter<'a>> {
+    /// cava-style bars with monstercat smoothing, gravity and a vertical gradient
+    fn get_canvas_bars<'a>(&'a self, inner_area: Rect) -> Canvas<'a, CanvasPainter<'a>> {
         Canvas::default()
             .marker(symbols::Marker::Braille)
             .x_bounds([0.0, inner_area.width.into()])
             .y_bounds([0.0, inner_area.height.into()])
             .paint(Box::new(move |ctx| {
-                use std::io::{BufReader, Read};
-                use std::process::{Command, Stdio};
-                use std::sync::Mutex;
-                use std::sync::OnceLock;
-                use std::thread;
-
-                if !is_cava_installed() {
-                    return;
-                }
-
-                static CAVA_DATA: OnceLock<Mutex<Vec<f32>>> = OnceLock::new();
-                static CAVA_RUNNING: OnceLock<Mutex<bool>> = OnceLock::new();
-
-                let cava_data =
-                    CAVA_DATA.get_or_init(|| Mutex::new(vec![0.0; inner_area.width as usize]));
-                let cava_running = CAVA_RUNNING.get_or_init(|| Mutex::new(false));
-
-                if !*cava_running.lock().unwrap() {
-                    let mut guard = cava_running.lock().unwrap();
-                    if !*guard {
-                        thread::spawn(move || {
-                            let config_content = format!(
-                                r#"
-                            [general]
-                            bars = {}
-                            framerate = 60
-                            autosens = 1
-
-                            [input]
-                            method = pulse
-                            source = auto
-
-                            [output]
-                            method = raw
-                            raw_target = /dev/stdout
-                            data_format = binary
-                            bit_format = 8bit
-                            orientation = top
-
-                            [color]
-                            gradient = 1
-                            gradient_count = 2
-                            gradient_color_1 = '#0099ff'
-                            gradient_color_2 = '#ff3399'
-
-                            [smoothing]
-                            monstercat = 1
-                            noise_reduction = 0.77
-
-                            [eq]
-                            1 = 1
-                            2 = 1
-                            3 = 1
-                            4 = 1
-                            5 = 1
-                            "#,
-                                inner_area.width
-                            );
-
-                            let temp_dir = std::env::temp_dir();
-                            let config_path = temp_dir.join("melovitui_cava.conf");
-                            std::fs::write(&config_path, config_content).unwrap();
-
-                            let mut cava = Command::new("cava")
-                                .arg("-p")
-                                .arg(&config_path)
-                                .stdout(Stdio::piped())
-                                .spawn()
-                                .unwrap();
-
-                            *cava_running.lock().unwrap() = true;
-
-                            let stdout = cava.stdout.take().unwrap();
-                            let mut reader = BufReader::new(stdout);
-                            let num_bars = inner_area.width as usize;
-                            let mut buffer = vec![0u8; num_bars];
-
-                            while reader.read_exact(&mut buffer).is_ok() {
-                                let mut data = cava_data.lock().unwrap();
-                                for (i, &val) in buffer.iter().enumerate() {
-                                    if i < num_bars {
-                                        data[i] = val as f32 / 255.0;
-                                    }
-                                }
-
-                                thread::sleep(std::time::Duration::from_millis(16));
-                            }
-
-                            *cava_running.lock().unwrap() = false;
-                            let _ = cava.wait();
-                        });
-
-                        *guard = true;
-                    }
-                }
-
-                // Rendering
                 let width = inner_area.width as f64;
                 let height = inner_area.height as f64;
 
@@ -425,51 +320,34 @@ impl VisualizerUI {
                     color: Color::Black,
                 });
 
-                let bar_values = {
-                    let data_guard = cava_data.lock().unwrap();
-                    data_guard.clone()
-                };
-
-                let num_bars = bar_values.len();
-                let bar_width = width / num_bars as f64;
+                let gradient = self.native_bars.settings().gradient_colors();
+                let bar_width = (BAR_STRIDE - 1) as f64;
 
-                for (i, &value) in bar_values.iter().enumerate() {
-                    let x = i as f64 * bar_width;
-                    let bar_height = value as f64 * height;
-
-                    // Color oscillation
-                    let color_phase = i as f64 / num_bars as f64 + time * 0.1;
-                    let r = ((color_phase * 2.0).sin() * 0.5 + 0.5) * 255.0;
-                    let g = ((color_phase * 2.0 + 2.0).sin() * 0.5 + 0.5) * 255.0;
-                    let b = ((color_phase * 2.0 + 4.0).sin() * 0.5 + 0.5) * 255.0;
-                    let color = Color::Rgb(r as u8, g as u8, b as u8);
-
-                    // Main bar (BOTTOM-UP)
-                    ctx.draw(&Rectangle {
-                        x,
-                        y: 0.0,
-                        width: bar_width - 1.0,
-                        height: bar_height,
-                        color,
-                    });
-
-                    // Peak line
-                    let peak_y = bar_height;
-                    if peak_y < height {
-                        // Use a lighter version of the bar color for peaks
-                        let peak_color = Color::Rgb(
-                            (r as u16 + 60).min(255) as u8,
-                            (g as u16 + 60).min(255) as u8,
-                            (b as u16 + 60).min(255) as u8,
-                        );
-
-                        ctx.draw(&Rectangle {
-                            x,
-                            y: peak_y,
-                            width: bar_width - 1.0,
-                            height: 1.0,
-                            color: peak_color,
-                        });
+                for (i, &value) in self.native_bars.heights().iter().enumerate() {
+                    let bar_height = f64::from(value) * height;
+                    if bar_height < 0.25 {
+                        continue;
+                    }
+                    let x = (i * BAR_STRIDE) as f64;
+
+                    // One segment per row so the color follows the height, like cava
+                    let mut y = 0.0;
+                    while y < bar_height {
+                        let top = (y + 1.0).min(bar_height);
+                        let color = gradient_color(&gradient, y / height);
+                        // Half-column steps fill both dot columns of a Braille cell
+                        let mut column = x;
+                        while column < x + bar_width {
+                            ctx.draw(&Line {
+                                x1: column,
+                                y1: y,
+                                x2: column,
+                                y2: top,
+                                color,
+                            });
+                            column += 0.5;
+                        }
+                        y = top;
                     }
                 }
             }))
@@ -479,7 +357,7 @@ impl VisualizerUI {
     fn get_canvas_2<'a>(
         &'a self,
         inner_area: Rect,
-        spectrum: &'a [f32],
+        bands: &'a Bands,
         time: f64,
     ) -> Canvas<CanvasPainter<'a>> {
         Canvas::default()
@@ -501,41 +379,13 @@ impl VisualizerUI {
                 });
 
                 // Bar resolution - use half the width since we'll mirror
-                // Increased density by using more of the available spectrum data
-                let num_samples = spectrum.len().min(inner_area.width as usize / 3);
-                // Adjusted ratio for narrower bars and less spacing to increase density
+                let num_samples = bands.values.len();
+                // Narrow bars with little spacing for a dense look
                 let bar_width = (width / 2.0) / num_samples as f64 * 0.7;
                 let bar_spacing = (width / 2.0) / num_samples as f64 * 0.3;
 
-                use std::sync::Mutex;
-                use std::sync::OnceLock;
-
-                static PEAKS: OnceLock<Mutex<Vec<f64>>> = OnceLock::new();
-                static SMOOTHED_VALUES: OnceLock<Mutex<Vec<f64>>> = OnceLock::new();
-
-                let peaks = PEAKS.get_or_init(|| Mutex::new(vec![0.0; num_samples]));
-                let smoothed = SMOOTHED_VALUES.get_or_init(|| Mutex::new(vec![0.0; num_samples]));
-
-                {
-                    let mut smoothed_data = smoothed.lock().unwrap();
-                    let mut peaks_data = peaks.lock().unwrap();
-
-                    for (i, &value) in spectrum.iter().enumerate().take(num_samples) {
-                        let alpha = 0.15;
-                        let normalized_value = f64::from(value).powf(0.6);
-                        smoothed_data[i] =
-                            smoothed_data[i] * (1.0 - alpha) + normalized_value * alpha;
-
-                        if smoothed_data[i] > peaks_data[i] {
-                            peaks_data[i] = smoothed_data[i];
-                        } else {
-                            peaks_data[i] *= 0.97;
-                        }
-                    }
-                }
-
-                let smoothed_values = smoothed.lock().unwrap().clone();
-                let peak_values = peaks.lock().unwrap().clone();
+                let smoothed_values: Vec<f64> = bands.values.iter().map(|&v| v.into()).collect();
+                let peak_values: Vec<f64> = bands.peaks.iter().map(|&v| v.into()).collect();
 
                 for i in 0..num_samples {
                     let bar_height = smoothed_values[i] * height;
@@ -654,7 +504,7 @@ impl VisualizerUI {
     fn get_canvas_3<'a>(
         &'a self,
         inner_area: Rect,
-        spectrum: &'a [f32],
+        bands: &'a Bands,
         time: f64,
     ) -> Canvas<CanvasPainter<'a>> {
         Canvas::default()
@@ -675,39 +525,12 @@ impl VisualizerUI {
                 });
 
                 // Use the full width for the bars
-                let num_bars = spectrum.len().min(inner_area.width as usize);
+                let num_bars = bands.values.len();
                 let bar_width = width / num_bars as f64 * 0.8;
                 let bar_spacing = width / num_bars as f64 * 0.2;
 
-                use std::sync::Mutex;
-                use std::sync::OnceLock;
-
-                static PEAKS: OnceLock<Mutex<Vec<f64>>> = OnceLock::new();
-                static SMOOTHED_VALUES: OnceLock<Mutex<Vec<f64>>> = OnceLock::new();
-
-                let peaks = PEAKS.get_or_init(|| Mutex::new(vec![0.0; num_bars]));
-                let smoothed = SMOOTHED_VALUES.get_or_init(|| Mutex::new(vec![0.0; num_bars]));
-
-                {
-                    let mut smoothed_data = smoothed.lock().unwrap();
-                    let mut peaks_data = peaks.lock().unwrap();
-
-                    for (i, &value) in spectrum.iter().enumerate().take(num_bars) {
-                        let alpha = 0.2;
-                        let normalized_value = f64::from(value).powf(0.7);
-                        smoothed_data[i] =
-                            smoothed_data[i] * (1.0 - alpha) + normalized_value * alpha;
-
-                        if smoothed_data[i] > peaks_data[i] {
-                            peaks_data[i] = smoothed_data[i];
-                        } else {
-                            peaks_data[i] *= 0.95;
-                        }
-                    }
-                }
-
-                let smoothed_values = smoothed.lock().unwrap().clone();
-                let peak_values = peaks.lock().unwrap().clone();
+                let smoothed_values: Vec<f64> = bands.values.iter().map(|&v| v.into()).collect();
+                let peak_values: Vec<f64> = bands.peaks.iter().map(|&v| v.into()).collect();
 
                 for i in 0..num_bars {
                     let x = i as f64 * (bar_width + bar_spacing);
@@ -772,7 +595,7 @@ impl VisualizerUI {
     fn get_canvas_4<'a>(
         &'a self,
         inner_area: Rect,
-        spectrum: &'a [f32],
+        bands: &'a Bands,
         time: f64,
     ) -> Canvas<CanvasPainter<'a>> {
         Canvas::default()
@@ -793,55 +616,15 @@ impl VisualizerUI {
                     color: Color::Rgb(10, 10, 20),
                 });
 
-                // Maximize bar density - use as many samples as possible
-                // Don't divide by anything to get the maximum number of bars that can fit
-                let num_samples = spectrum.len().min(inner_area.width as usize);
+                // One band per column of each mirrored half
+                let num_samples = bands.values.len();
 
                 // Use minimal spacing between bars to maximize density
                 let bar_width = (width / 2.0) / num_samples as f64 * 0.95; // Almost no gap
                 let bar_spacing = (width / 2.0) / num_samples as f64 * 0.05; // Tiny spacing
 
-                use std::sync::Mutex;
-                use std::sync::OnceLock;
-
-                // Use static data for each canvas type to prevent state confusion
-                static PEAKS_4: OnceLock<Mutex<Vec<f64>>> = OnceLock::new();
-                static SMOOTHED_VALUES_4: OnceLock<Mutex<Vec<f64>>> = OnceLock::new();
-
-                let peaks = PEAKS_4.get_or_init(|| Mutex::new(vec![0.0; num_samples]));
-                let smoothed = SMOOTHED_VALUES_4.get_or_init(|| Mutex::new(vec![0.0; num_samples]));
-
-                {
-                    let mut smoothed_data = smoothed.lock().unwrap();
-                    let mut peaks_data = peaks.lock().unwrap();
-
-                    // Resize if needed (in case window size changed)
-                    if smoothed_data.len() != num_samples {
-                        *smoothed_data = vec![0.0; num_samples];
-                        *peaks_data = vec![0.0; num_samples];
-                    }
-
-                    for (i, &value) in spectrum.iter().enumerate().take(num_samples) {
-                        // Apply frequency filtering - reduce high frequencies
-                        let frequency_factor = i as f64 / num_samples as f64;
-                        // This filter will progressively reduce values as frequency increases
-                        let high_freq_filter = (1.0 - frequency_factor.powf(0.75)).max(0.15);
-
-                        let alpha = 0.15;
-                        let normalized_value = f64::from(value).powf(0.6) * high_freq_filter;
-                        smoothed_data[i] =
-                            smoothed_data[i] * (1.0 - alpha) + normalized_value * alpha;
-
-                        if smoothed_data[i] > peaks_data[i] {
-                            peaks_data[i] = smoothed_data[i];
-                        } else {
-                            peaks_data[i] *= 0.97;
-                        }
-                    }
-                }
-
-                let smoothed_values = smoothed.lock().unwrap().clone();
-                let peak_values = peaks.lock().unwrap().clone();
+                let smoothed_values: Vec<f64> = bands.values.iter().map(|&v| v.into()).collect();
+                let peak_values: Vec<f64> = bands.peaks.iter().map(|&v| v.into()).collect();
 
                 // For maximum density, use a simple skip pattern to avoid rendering every single bar
                 // when the number is extremely high (which would slow down rendering)
@@ -990,10 +773,19 @@ fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (u8, u8, u8) {
     )
 }
 
-fn is_cava_installed() -> bool {
-    Command::new("cava")
-        .arg("-v")
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+/// Color at `position` (0 to 1) of a gradient through the given colors
+fn gradient_color(colors: &[(u8, u8, u8)], position: f64) -> Color {
+    let Some(&last) = colors.last() else {
+        return Color::White;
+    };
+    let scaled = position.clamp(0.0, 1.0) * (colors.len() - 1) as f64;
+    let index = scaled.floor() as usize;
+    if index + 1 >= colors.len() {
+        return Color::Rgb(last.0, last.1, last.2);
+    }
+
+    let t = scaled - index as f64;
+    let (from, to) = (colors[index], colors[index + 1]);
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    Color::Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
 }
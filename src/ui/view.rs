@@ -1,18 +1,31 @@
+use std::sync::Arc;
+
 use ratatui::{
     layout::{Constraint, Direction, Layout},
     Frame,
 };
 
-use crate::{app::App, controls::playback_state::PlaybackStatus};
+use crate::{
+    app::App,
+    config::{get_band_settings, get_bar_settings},
+    controls::{music_library::LibraryLayout, playback_state::PlaybackStatus},
+};
 
 use super::components::{
-    help_ui::HelpUI, music_library_ui::MusicLibraryUI, playback_control_ui::PlaybackControlUI,
-    sound_control_ui::SoundControlUI, visualizer_ui::VisualizerUI,
+    album_browser_ui::AlbumBrowserUI, equalizer_ui::EqualizerUI, folder_tree_ui::FolderTreeUI,
+    help_ui::HelpUI, music_library_ui::MusicLibraryUI, play_queue_ui::PlayQueueUI,
+    playback_control_ui::PlaybackControlUI, playlist_sidebar_ui::PlaylistSidebarUI,
+    scan_report_ui::ScanReportUI, sound_control_ui::SoundControlUI, visualizer_ui::VisualizerUI,
 };
 
 pub struct UIManager {
     music_library: MusicLibraryUI,
+    folder_tree: FolderTreeUI,
+    album_browser: AlbumBrowserUI,
+    playlist_sidebar: PlaylistSidebarUI,
+    play_queue: PlayQueueUI,
     sound_control: SoundControlUI,
+    equalizer: EqualizerUI,
     playback_controls: PlaybackControlUI,
     visualizer: VisualizerUI,
 }
@@ -21,8 +34,13 @@ impl UIManager {
     pub fn new() -> Self {
         Self {
             music_library: MusicLibraryUI::new(),
-            visualizer: VisualizerUI::new(),
+            folder_tree: FolderTreeUI::new(),
+            album_browser: AlbumBrowserUI::new(),
+            playlist_sidebar: PlaylistSidebarUI::new(),
+            play_queue: PlayQueueUI::new(),
+            visualizer: VisualizerUI::new(get_band_settings(), get_bar_settings()),
             sound_control: SoundControlUI::new(),
+            equalizer: EqualizerUI::new(),
             playback_controls: PlaybackControlUI::new(),
         }
     }
@@ -34,6 +52,21 @@ impl UIManager {
             .constraints([Constraint::Ratio(2, 10), Constraint::Ratio(8, 10)])
             .split(frame.area());
 
+        // Playlist sidebar above the track list and the play queue below it,
+        // each never more than a third of the column
+        let playlists_state = app.get_playlists_state();
+        let play_queue_state = app.get_play_queue_state();
+        let sidebar_height = PlaylistSidebarUI::height(&playlists_state.lock());
+        let queue_height = PlayQueueUI::height(&play_queue_state.lock());
+        let library_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(sidebar_height.min(chunks[0].height / 3)),
+                Constraint::Min(0),
+                Constraint::Length(queue_height.min(chunks[0].height / 3)),
+            ])
+            .split(chunks[0]);
+
         let main_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -47,6 +80,11 @@ impl UIManager {
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
             .split(main_layout[1]);
 
+        let sound_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(control_chunks[0]);
+
         let (library_state, sound_state, playback_state) = (
             app.get_library_state(),
             app.get_sound_state(),
@@ -56,13 +94,14 @@ impl UIManager {
         let sound = app.get_audio_system();
         let sound_lock = sound.lock();
         let spectrum = sound_lock.get_current_frame();
+        let spectrum_error = sound_lock.get_spectrum_error();
         let canvas_type = sound_lock.get_visualizer_canvas_type();
 
         let song_text = {
             let playback_state = playback_state.lock();
             let library_state = library_state.lock();
             if let Some(idx) = playback_state.current_track {
-                let track_title = library_state.tracks[idx].title.clone();
+                let track_title = library_state.tracks[idx].display_name();
 
                 match playback_state.status {
                     PlaybackStatus::Stopped => format!("⏹ {}", track_title), // Stop button
@@ -75,11 +114,53 @@ impl UIManager {
         };
 
         // Render main UI components
-        self.music_library.render(frame, chunks[0], library_state);
+        self.playlist_sidebar
+            .render(frame, library_chunks[0], Arc::clone(&playlists_state));
+        // Playlists show in the same pane as the library
+        let library_shown = playlists_state.lock().view.is_none();
+        if library_shown && app.library_layout == LibraryLayout::Folders {
+            self.folder_tree.render(
+                frame,
+                library_chunks[1],
+                Arc::clone(&library_state),
+                app.get_folder_tree_state(),
+                app.get_viewport_state(),
+            );
+        } else if library_shown && app.library_layout == LibraryLayout::Albums {
+            self.album_browser.render(
+                frame,
+                library_chunks[1],
+                Arc::clone(&library_state),
+                app.get_album_browser_state(),
+                app.get_viewport_state(),
+            );
+        } else {
+            self.music_library.render(
+                frame,
+                library_chunks[1],
+                Arc::clone(&library_state),
+                playlists_state,
+                app.get_search_state(),
+                app.get_viewport_state(),
+            );
+        }
+        self.play_queue.render(
+            frame,
+            library_chunks[2],
+            play_queue_state,
+            library_state,
+            app.queue_focused,
+        );
         self.visualizer
-            .render(frame, main_layout[0], spectrum, canvas_type);
+            .render(frame, main_layout[0], spectrum, canvas_type, spectrum_error);
         self.sound_control
-            .render(frame, control_chunks[0], sound_state);
+            .render(frame, sound_chunks[0], sound_state);
+        self.equalizer.render(
+            frame,
+            sound_chunks[1],
+            app.get_graphic_eq_state(),
+            app.equalizer_focused,
+        );
         self.playback_controls
             .render(frame, control_chunks[1], playback_state, song_text);
 
@@ -89,5 +170,11 @@ impl UIManager {
             // no need to intantiate an object for this
             HelpUI::render(frame, app.get_keybindings());
         }
+
+        if app.show_scan_report {
+            let library = app.get_library_state();
+            let library = library.lock();
+            ScanReportUI::render(frame, &library.skipped, &library.roots);
+        }
     }
 }